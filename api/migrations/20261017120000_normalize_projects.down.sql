@@ -0,0 +1,58 @@
+CREATE TABLE projects_json (
+    id uuid PRIMARY KEY,
+    project jsonb NOT NULL
+);
+
+INSERT INTO projects_json (id, project)
+SELECT
+    p.id,
+    jsonb_strip_nulls(jsonb_build_object(
+        'id', p.id,
+        'title', p.title,
+        'description', p.description,
+        'image', p.image,
+        'color', p.color,
+        'created', p.created,
+        'modified', p.modified
+    )) || jsonb_build_object(
+        'owner', (
+            SELECT jsonb_build_object('email', m.email, 'name', m.name, 'surname', m.surname)
+            FROM project_members m
+            WHERE m.project_id = p.id AND m.role = 'owner'
+        ),
+        'editors', COALESCE((
+            SELECT jsonb_agg(jsonb_build_object('email', m.email, 'name', m.name, 'surname', m.surname))
+            FROM project_members m
+            WHERE m.project_id = p.id AND m.role = 'editor'
+        ), '[]'),
+        'viewers', COALESCE((
+            SELECT jsonb_agg(jsonb_build_object('email', m.email, 'name', m.name, 'surname', m.surname))
+            FROM project_members m
+            WHERE m.project_id = p.id AND m.role = 'viewer'
+        ), '[]'),
+        'views', COALESCE((
+            SELECT jsonb_agg(jsonb_build_object('id', v.id, 'title', v.title, 'permalink', v.permalink) ORDER BY v.position)
+            FROM project_views v
+            WHERE v.project_id = p.id
+        ), '[]'),
+        'assets', COALESCE((
+            SELECT jsonb_agg(jsonb_build_object('key', a.key, 'name', a.name, 'clampToGround', a.clamp_to_ground) ORDER BY a.position)
+            FROM project_assets a
+            WHERE a.project_id = p.id
+        ), '[]'),
+        'geometries', COALESCE((
+            SELECT jsonb_agg(g.geometry ORDER BY g.position)
+            FROM project_geometries g
+            WHERE g.project_id = p.id
+        ), '[]')
+    )
+FROM projects p;
+
+DROP TABLE project_geometries;
+DROP TABLE project_assets;
+DROP TABLE project_views;
+DROP TABLE project_members;
+DROP TABLE projects;
+DROP TYPE project_role;
+
+ALTER TABLE projects_json RENAME TO projects;
@@ -0,0 +1,102 @@
+ALTER TABLE projects RENAME TO projects_json;
+
+CREATE TYPE project_role AS ENUM ('viewer', 'editor', 'owner');
+
+CREATE TABLE projects (
+    id uuid PRIMARY KEY,
+    title text NOT NULL,
+    description text,
+    image text,
+    color text NOT NULL,
+    created timestamptz NOT NULL,
+    modified timestamptz
+);
+
+CREATE TABLE project_members (
+    project_id uuid NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    email text NOT NULL CHECK (email = LOWER(email)),
+    name text NOT NULL,
+    surname text NOT NULL,
+    role project_role NOT NULL,
+    PRIMARY KEY (project_id, email)
+);
+
+CREATE INDEX project_members_email_idx ON project_members (email);
+CREATE UNIQUE INDEX project_members_owner_idx ON project_members (project_id) WHERE role = 'owner';
+
+CREATE TABLE project_views (
+    project_id uuid NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    position integer NOT NULL,
+    id text NOT NULL,
+    title text NOT NULL,
+    permalink text NOT NULL,
+    PRIMARY KEY (project_id, position)
+);
+
+CREATE TABLE project_assets (
+    project_id uuid NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    position integer NOT NULL,
+    key text NOT NULL,
+    name text NOT NULL,
+    clamp_to_ground boolean,
+    PRIMARY KEY (project_id, position)
+);
+
+CREATE INDEX project_assets_key_idx ON project_assets (key);
+
+CREATE TABLE project_geometries (
+    project_id uuid NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    position integer NOT NULL,
+    id text,
+    geometry jsonb NOT NULL,
+    PRIMARY KEY (project_id, position)
+);
+
+INSERT INTO projects (id, title, description, image, color, created, modified)
+SELECT
+    id,
+    project->>'title',
+    project->>'description',
+    project->>'image',
+    project->>'color',
+    (project->>'created')::timestamptz,
+    (project->>'modified')::timestamptz
+FROM projects_json;
+
+-- Members appearing multiple times keep their highest role.
+INSERT INTO project_members (project_id, email, name, surname, role)
+SELECT DISTINCT ON (project_id, email) project_id, email, name, surname, role
+FROM (
+    SELECT id AS project_id, LOWER(project->'owner'->>'email') AS email,
+           COALESCE(project->'owner'->>'name', '') AS name,
+           COALESCE(project->'owner'->>'surname', '') AS surname,
+           'owner'::project_role AS role
+    FROM projects_json
+    UNION ALL
+    SELECT id, LOWER(member->>'email'), COALESCE(member->>'name', ''),
+           COALESCE(member->>'surname', ''), 'editor'::project_role
+    FROM projects_json, jsonb_array_elements(COALESCE(project->'editors', '[]')) AS member
+    UNION ALL
+    SELECT id, LOWER(member->>'email'), COALESCE(member->>'name', ''),
+           COALESCE(member->>'surname', ''), 'viewer'::project_role
+    FROM projects_json, jsonb_array_elements(COALESCE(project->'viewers', '[]')) AS member
+) AS members
+ORDER BY project_id, email, role DESC;
+
+INSERT INTO project_views (project_id, position, id, title, permalink)
+SELECT id, view.position - 1, view.value->>'id', view.value->>'title', view.value->>'permalink'
+FROM projects_json,
+     jsonb_array_elements(COALESCE(project->'views', '[]')) WITH ORDINALITY AS view(value, position);
+
+INSERT INTO project_assets (project_id, position, key, name, clamp_to_ground)
+SELECT id, asset.position - 1, asset.value->>'key', asset.value->>'name',
+       (asset.value->>'clampToGround')::boolean
+FROM projects_json,
+     jsonb_array_elements(COALESCE(project->'assets', '[]')) WITH ORDINALITY AS asset(value, position);
+
+INSERT INTO project_geometries (project_id, position, id, geometry)
+SELECT id, geometry.position - 1, geometry.value->>'id', geometry.value
+FROM projects_json,
+     jsonb_array_elements(COALESCE(project->'geometries', '[]')) WITH ORDINALITY AS geometry(value, position);
+
+DROP TABLE projects_json;
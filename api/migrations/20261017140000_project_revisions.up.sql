@@ -0,0 +1,9 @@
+CREATE TABLE project_revisions (
+    project_id uuid NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    revision integer NOT NULL,
+    author text NOT NULL,
+    created timestamptz NOT NULL,
+    changes text[],
+    snapshot jsonb NOT NULL,
+    PRIMARY KEY (project_id, revision)
+);
@@ -0,0 +1,5 @@
+ALTER TABLE project_assets
+    DROP COLUMN original_name,
+    DROP COLUMN format;
+
+DROP TYPE asset_format;
@@ -0,0 +1,8 @@
+CREATE TYPE asset_format AS ENUM ('kml', 'kmz', 'geojson', 'gpx', 'csv', 'shapefile');
+
+ALTER TABLE project_assets
+    ADD COLUMN original_name text,
+    ADD COLUMN format asset_format;
+
+-- Assets uploaded so far could only be KML files.
+UPDATE project_assets SET format = 'kml';
@@ -0,0 +1,12 @@
+CREATE TABLE project_invitations (
+    id uuid PRIMARY KEY,
+    project_id uuid NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    role project_role NOT NULL CHECK (role <> 'owner'),
+    created_by text NOT NULL,
+    created timestamptz NOT NULL,
+    expires timestamptz NOT NULL,
+    single_use boolean NOT NULL,
+    redemptions integer NOT NULL DEFAULT 0
+);
+
+CREATE INDEX project_invitations_project_id_idx ON project_invitations (project_id);
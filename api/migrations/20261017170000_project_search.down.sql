@@ -0,0 +1,5 @@
+DROP INDEX projects_text_idx;
+
+ALTER TABLE project_geometries DROP COLUMN extent;
+
+DROP FUNCTION geometry_extent(jsonb);
@@ -0,0 +1,25 @@
+-- Approximates the WGS84 extent of a geometry from its ECEF positions.
+-- Heights are neglected, which is precise enough to filter by area.
+CREATE FUNCTION geometry_extent(geometry jsonb) RETURNS box
+    LANGUAGE sql IMMUTABLE PARALLEL SAFE
+AS $$
+SELECT box(point(min(lon), min(lat)), point(max(lon), max(lat)))
+FROM (
+    SELECT degrees(atan2(y, x)) AS lon,
+           degrees(atan2(z, sqrt(x ^ 2 + y ^ 2) * (1 - 0.00669437999014))) AS lat
+    FROM (
+        SELECT (position->>'x')::float8 AS x,
+               (position->>'y')::float8 AS y,
+               (position->>'z')::float8 AS z
+        FROM jsonb_array_elements(COALESCE(geometry->'positions', '[]')) AS position
+    ) AS ecef
+) AS wgs84
+$$;
+
+ALTER TABLE project_geometries
+    ADD COLUMN extent box GENERATED ALWAYS AS (geometry_extent(geometry)) STORED;
+
+CREATE INDEX project_geometries_extent_idx ON project_geometries USING gist (extent);
+
+CREATE INDEX projects_text_idx ON projects
+    USING gin (to_tsvector('simple', title || ' ' || COALESCE(description, '')));
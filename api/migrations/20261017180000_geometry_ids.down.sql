@@ -0,0 +1,3 @@
+DROP INDEX project_geometries_id_idx;
+
+ALTER TABLE project_geometries ALTER COLUMN id DROP NOT NULL;
@@ -0,0 +1,21 @@
+-- Geometries are addressed by their id, so every geometry needs one that is unique within its project.
+WITH renamed AS (
+    SELECT project_id, position, gen_random_uuid()::text AS id
+    FROM (
+        SELECT
+            project_id,
+            position,
+            id,
+            row_number() OVER (PARTITION BY project_id, id ORDER BY position) AS occurrence
+        FROM project_geometries
+    ) geometries
+    WHERE id IS NULL OR id = '' OR occurrence > 1
+)
+UPDATE project_geometries g
+SET id = renamed.id, geometry = jsonb_set(g.geometry, '{id}', to_jsonb(renamed.id))
+FROM renamed
+WHERE g.project_id = renamed.project_id AND g.position = renamed.position;
+
+ALTER TABLE project_geometries ALTER COLUMN id SET NOT NULL;
+
+CREATE UNIQUE INDEX project_geometries_id_idx ON project_geometries (project_id, id);
@@ -0,0 +1,7 @@
+DROP TABLE audit_log;
+
+DROP FUNCTION audit_log_append_only();
+
+DROP TYPE audit_target;
+
+DROP TYPE audit_action;
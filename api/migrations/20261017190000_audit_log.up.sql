@@ -0,0 +1,37 @@
+CREATE TYPE audit_action AS ENUM (
+    'project_created',
+    'project_deleted',
+    'members_changed',
+    'assets_deleted',
+    'layer_config_accessed'
+);
+
+CREATE TYPE audit_target AS ENUM ('project', 'layer_config');
+
+-- Entries are never changed or removed, and are kept after their target has been deleted.
+CREATE TABLE audit_log (
+    id bigserial PRIMARY KEY,
+    created timestamptz NOT NULL DEFAULT now(),
+    actor text NOT NULL,
+    action audit_action NOT NULL,
+    target_type audit_target NOT NULL,
+    target_id text,
+    diff jsonb NOT NULL DEFAULT '{}'
+);
+
+CREATE INDEX audit_log_target_idx ON audit_log (target_type, target_id, id);
+CREATE INDEX audit_log_actor_idx ON audit_log (actor, id);
+
+CREATE FUNCTION audit_log_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_append_only
+    BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_append_only();
+
+CREATE TRIGGER audit_log_no_truncate
+    BEFORE TRUNCATE ON audit_log
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_log_append_only();
@@ -0,0 +1,7 @@
+-- Enum values can't be removed, so `project_restored` is left in place.
+
+DELETE FROM project_revisions WHERE project_id NOT IN (SELECT id FROM projects);
+
+ALTER TABLE project_revisions
+    ADD CONSTRAINT project_revisions_project_id_fkey
+    FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE CASCADE;
@@ -0,0 +1,4 @@
+-- Revisions are kept after their project has been deleted, so that it can be restored by administrators.
+ALTER TABLE project_revisions DROP CONSTRAINT project_revisions_project_id_fkey;
+
+ALTER TYPE audit_action ADD VALUE IF NOT EXISTS 'project_restored';
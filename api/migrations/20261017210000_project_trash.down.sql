@@ -0,0 +1,3 @@
+DELETE FROM projects WHERE deleted IS NOT NULL;
+
+ALTER TABLE projects DROP COLUMN deleted;
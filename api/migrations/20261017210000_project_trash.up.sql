@@ -0,0 +1,4 @@
+-- Deleted projects are kept in the trash until they are purged after a retention period.
+ALTER TABLE projects ADD COLUMN deleted timestamptz;
+
+CREATE INDEX projects_deleted_idx ON projects (deleted) WHERE deleted IS NOT NULL;
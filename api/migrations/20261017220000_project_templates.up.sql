@@ -0,0 +1 @@
+ALTER TABLE projects ADD COLUMN template boolean NOT NULL DEFAULT false;
@@ -0,0 +1 @@
+-- Enum values can't be removed, so `project_purged` is left in place.
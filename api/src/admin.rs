@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::extract::{Extension, FromRequestParts};
+use axum::routing::{get, post, put};
+use axum::{Router, async_trait, http::request::Parts};
+
+use crate::Error;
+use crate::audit;
+use crate::auth::Claims;
+use crate::handlers;
+use crate::projects::admin as projects;
+
+/// Configuration of the administration endpoints.
+#[derive(clap::Parser, Clone, Debug)]
+pub struct AdminConfig {
+    /// The cognito group whose members are administrators.
+    #[clap(long, env, default_value = "ngm-admin")]
+    pub admin_group: String,
+}
+
+/// An authenticated administrator.
+///
+/// Users that are not members of [AdminConfig::admin_group] are answered with `403 Forbidden`.
+pub struct Admin {
+    pub claims: Claims,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Admin
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request_parts(parts, state).await?;
+        let Extension(config) = Extension::<Arc<AdminConfig>>::from_request_parts(parts, state)
+            .await
+            .context("Admin configuration is not available")?;
+        if !claims.cognito_groups.contains(&config.admin_group) {
+            return Err(Error::Forbidden);
+        }
+        Ok(Self { claims })
+    }
+}
+
+/// Creates the router serving all administration endpoints.
+/// It is expected to be nested under `/api/admin`.
+pub fn router() -> Router {
+    Router::new()
+        .route("/projects", get(projects::list_projects))
+        .route("/projects/deleted", get(projects::list_deleted_projects))
+        .route("/projects/:id/owner", put(projects::reassign_owner))
+        .route("/projects/:id/restore", post(projects::restore_project))
+        .route("/storage", get(projects::get_storage_usage))
+        .route("/audit", get(audit::list_audit_entries))
+        .route("/layers/reload", post(handlers::reload_layer_config))
+}
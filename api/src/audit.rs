@@ -0,0 +1,159 @@
+use axum::extract::{Extension, Json, Query};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::Result;
+use crate::admin::Admin;
+
+/// The number of entries returned by default.
+const DEFAULT_LIMIT: i64 = 100;
+
+/// The largest number of entries that can be requested at once.
+const MAX_LIMIT: i64 = 1000;
+
+/// A security-relevant action.
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "audit_action", rename_all = "snake_case")]
+#[serde(rename_all = "camelCase")]
+pub enum AuditAction {
+    ProjectCreated,
+    /// A project has been moved to the trash.
+    ProjectDeleted,
+    /// A deleted project has been restored, either from the trash or by an administrator.
+    ProjectRestored,
+    MembersChanged,
+    AssetsDeleted,
+    /// A project has been permanently deleted after its retention period in the trash.
+    ProjectPurged,
+    /// Layers restricted to specific cognito groups have been served to a member of these groups.
+    LayerConfigAccessed,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "audit_target", rename_all = "snake_case")]
+#[serde(rename_all = "camelCase")]
+pub enum AuditTargetType {
+    Project,
+    LayerConfig,
+}
+
+/// The object affected by an audited action.
+pub enum AuditTarget {
+    Project(Uuid),
+    LayerConfig,
+}
+
+/// An entry of the audit log.
+#[derive(Serialize, Clone, Debug, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: i64,
+    pub created: DateTime<Utc>,
+
+    /// The email of the user that performed the action.
+    pub actor: String,
+    pub action: AuditAction,
+    pub target_type: AuditTargetType,
+    pub target_id: Option<String>,
+
+    /// What the action changed. The content depends on the action.
+    pub diff: Value,
+}
+
+/// The filters of an audit log query.
+/// Entries are returned most recent first.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditQuery {
+    pub actor: Option<String>,
+    pub action: Option<AuditAction>,
+    pub target_type: Option<AuditTargetType>,
+    pub target_id: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+
+    /// Only return entries preceding the one with this id.
+    /// Used to page through the log.
+    pub before: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+impl AuditEntry {
+    /// Appends an entry to the audit log.
+    ///
+    /// If `conn` is part of a transaction, the entry is only kept if the transaction is committed,
+    /// which makes it part of the audited change.
+    pub async fn record(
+        conn: &mut PgConnection,
+        actor: &str,
+        action: AuditAction,
+        target: AuditTarget,
+        diff: Value,
+    ) -> Result<()> {
+        let (target_type, target_id) = match target {
+            AuditTarget::Project(id) => (AuditTargetType::Project, Some(id.to_string())),
+            AuditTarget::LayerConfig => (AuditTargetType::LayerConfig, None),
+        };
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_log (actor, action, target_type, target_id, diff)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            actor.to_lowercase(),
+            action as AuditAction,
+            target_type as AuditTargetType,
+            target_id,
+            diff,
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Loads the entries matching a query.
+    pub async fn search(conn: &mut PgConnection, query: &AuditQuery) -> Result<Vec<Self>> {
+        let mut sql = QueryBuilder::<Postgres>::new(
+            "SELECT id, created, actor, action, target_type, target_id, diff FROM audit_log WHERE true",
+        );
+        if let Some(actor) = &query.actor {
+            sql.push(" AND actor = ").push_bind(actor.to_lowercase());
+        }
+        if let Some(action) = query.action {
+            sql.push(" AND action = ").push_bind(action);
+        }
+        if let Some(target_type) = query.target_type {
+            sql.push(" AND target_type = ").push_bind(target_type);
+        }
+        if let Some(target_id) = &query.target_id {
+            sql.push(" AND target_id = ").push_bind(target_id.clone());
+        }
+        if let Some(from) = query.from {
+            sql.push(" AND created >= ").push_bind(from);
+        }
+        if let Some(to) = query.to {
+            sql.push(" AND created < ").push_bind(to);
+        }
+        if let Some(before) = query.before {
+            sql.push(" AND id < ").push_bind(before);
+        }
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        sql.push(" ORDER BY id DESC LIMIT ").push_bind(limit);
+
+        let entries = sql.build_query_as().fetch_all(&mut *conn).await?;
+        Ok(entries)
+    }
+}
+
+/// Queries the audit log.
+#[axum_macros::debug_handler]
+pub async fn list_audit_entries(
+    Extension(pool): Extension<PgPool>,
+    _admin: Admin,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>> {
+    let mut conn = pool.acquire().await?;
+    Ok(Json(AuditEntry::search(&mut conn, &query).await?))
+}
@@ -69,6 +69,12 @@ pub struct Claims {
     iss: String,
     pub email: String,
 
+    #[serde(default)]
+    pub given_name: String,
+
+    #[serde(default)]
+    pub family_name: String,
+
     #[serde(rename = "cognito:groups")]
     pub cognito_groups: Vec<String>,
 }
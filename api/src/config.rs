@@ -1,14 +1,36 @@
-use crate::{auth::Auth, database::Database};
+use crate::{AssetGc, LayerValidation, auth::Auth, database::Database};
 use serde::Serialize;
 
 #[derive(clap::Parser)]
 pub struct Config {
+    /// Validate the layer config files and print a report of the issues found, then exit.
     #[clap(long = "validate-only")]
     pub should_only_validate: bool,
 
+    #[clap(flatten)]
+    pub layers_validation: LayerValidation,
+
+    /// Run the garbage collection of project assets once, then exit.
+    #[clap(long = "gc-assets")]
+    pub should_only_gc_assets: bool,
+
+    /// Print the JSON Schema of the layer config files, then exit.
+    #[clap(long = "layers-schema")]
+    pub should_only_print_layers_schema: bool,
+
+    #[clap(flatten)]
+    pub asset_gc: AssetGc,
+
+    /// The layer config file, as a path or an `s3://` or `https://` URL.
+    /// Files that it includes are resolved relative to it.
     #[clap(long, env)]
     pub layers_file: String,
 
+    /// The interval at which the layer config files are checked for changes, in seconds.
+    /// Changed files are reloaded without restarting the API. Zero disables the check.
+    #[clap(long, env, default_value = "10")]
+    pub layers_reload_interval: u64,
+
     #[clap(flatten)]
     pub database: Database,
 
@@ -29,6 +29,18 @@ pub enum Error {
     #[error("an internal server error occurred")]
     Anyhow(#[from] anyhow::Error),
 
+    /// Return `422 Unprocessable Entity` for an asset that can't be converted.
+    #[error("{0}")]
+    InvalidAsset(#[from] crate::projects::ConversionError),
+
+    /// Return `422 Unprocessable Entity` for a layer config that can't be loaded.
+    #[error("{0}")]
+    InvalidLayerConfig(String),
+
+    /// Return `422 Unprocessable Entity` listing the invalid fields of a request.
+    #[error("request contains invalid fields")]
+    Validation(Vec<crate::validation::FieldError>),
+
     /// Return a custom api error.
     #[error("an api error occurred")]
     Api(StatusCode, &'static str),
@@ -41,6 +53,9 @@ impl Error {
             Self::Forbidden | Self::Jwt(_) => StatusCode::FORBIDDEN,
             Self::NotFound => StatusCode::NOT_FOUND,
             Self::Sqlx(_) | Self::Anyhow(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::InvalidAsset(_) | Self::InvalidLayerConfig(_) | Self::Validation(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
             Self::Api(code, _) => *code,
         }
     }
@@ -64,6 +79,18 @@ impl IntoResponse for Error {
                     .into_response();
             }
 
+            Self::Validation(ref errors) => {
+                return (
+                    self.status_code(),
+                    Json(json!({
+                      "status": self.status_code().as_u16(),
+                      "message": self.to_string(),
+                      "errors": errors,
+                    })),
+                )
+                    .into_response();
+            }
+
             Self::Jwt(m) => {
                 tracing::error!("Jsonwebtoken error: {:?}", m);
                 m.to_owned()
@@ -1,8 +1,9 @@
 use crate::{Layer, LayerConfig, LayerGroup, LayerGroupChild, LayerGroupOrReference};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all(serialize = "camelCase"))]
 pub struct LayerAccess {
     /// A list of cognito groups.
@@ -96,6 +97,22 @@ impl Filter for LayerGroupOrReference {
     }
 }
 
+impl LayerConfig {
+    /// The ids of the layers that are only accessible to members of specific cognito groups.
+    pub fn group_restricted_layer_ids(&self) -> Vec<&str> {
+        self.layers
+            .iter()
+            .filter(|layer| {
+                layer
+                    .access
+                    .as_ref()
+                    .is_some_and(|it| !it.groups.is_empty())
+            })
+            .map(|layer| layer.id.as_str())
+            .collect()
+    }
+}
+
 fn is_access_allowed(access: Option<&LayerAccess>, context: &FilterContext) -> bool {
     let Some(access) = access else {
         return true;
@@ -1,10 +1,17 @@
+use crate::layers::location::LayerDocuments;
+use crate::layers::report::{LineIndex, join_index, join_key};
 use crate::layers::*;
 use anyhow::anyhow;
+use schemars::JsonSchema;
+use schemars::generate::SchemaSettings;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
-use std::path::Path;
+use serde_json::Value;
+use serde_path_to_error::Segment;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Display;
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all(serialize = "camelCase"))]
 pub struct LayerConfig {
     /// A list of configs that should be merged into this one.
@@ -39,15 +46,33 @@ pub struct LayerConfig {
     /// Each entry's key is used to identify it within this config.
     #[serde(default, skip_serializing)]
     pub order_of_properties: HashMap<String, Vec<String>>,
+
+    /// The files from which this config has been parsed, including the included ones.
+    #[serde(skip)]
+    pub sources: Vec<LayerLocation>,
+
+    /// The ids of the layers and groups that could not be deserialized, including the included ones.
+    /// As these entries have already been reported, references to them are left out silently.
+    #[serde(skip)]
+    pub invalid_ids: BTreeSet<String>,
 }
 
 pub(in crate::layers) trait Parse: Sized {
     fn parse(self, context: &mut ParseContext) -> anyhow::Result<Self>;
 }
 
-pub(in crate::layers) struct ParseContext {
-    /// A string displaying the path to the config.
-    pub display: String,
+pub(in crate::layers) struct ParseContext<'a> {
+    /// The location of the config.
+    pub location: LayerLocation,
+
+    /// The lines at which the config's entries are located.
+    pub lines: LineIndex,
+
+    /// The JSON path of the entry that is currently being parsed.
+    pub path: String,
+
+    /// The issues found so far, within this config and the others parsed along with it.
+    pub diagnostics: &'a mut Vec<Diagnostic>,
 
     /// The config that is currently being parsed.
     /// Note that this config is most likely incomplete,
@@ -65,65 +90,181 @@ pub(in crate::layers) struct ParseContext {
     pub known_groups: BTreeMap<String, LayerGroup>,
 }
 
-impl ParseContext {
-    fn merge(&mut self, other: LayerConfig) -> anyhow::Result<()> {
+impl ParseContext<'_> {
+    /// The location of the entry that is currently being parsed.
+    pub fn origin(&self) -> Origin {
+        Origin {
+            location: self.location.clone(),
+            line: self.lines.line(&self.path),
+            path: self.path.clone(),
+        }
+    }
+
+    /// Reports an issue with the entry that is currently being parsed.
+    pub fn report(&mut self, kind: DiagnosticKind, message: impl Display) {
+        self.report_at(None, kind, message)
+    }
+
+    /// Reports an issue with the entry at `origin`,
+    /// or with the entry that is currently being parsed if `origin` is unknown.
+    fn report_at(&mut self, origin: Option<&Origin>, kind: DiagnosticKind, message: impl Display) {
+        let diagnostic = match origin {
+            Some(origin) => origin.diagnostic(kind, message),
+            None => self.origin().diagnostic(kind, message),
+        };
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn merge(&mut self, other: LayerConfig) {
+        self.config.invalid_ids.extend(other.invalid_ids);
         for layer in other.layers {
-            self.add_layer(layer)?
+            self.add_layer(layer)
         }
         for group in other.groups {
             match group {
                 LayerGroupOrReference::Definition(group) => {
-                    if self.known_groups.contains_key(&group.id) {
-                        return Err(anyhow!("group \"{}\" is defined multiple times", group.id));
+                    if self.check_unique_id(&group.id, "group", group.origin.as_deref()) {
+                        self.known_groups.insert(group.id.clone(), group);
                     }
-                    self.known_groups.insert(group.id.clone(), group.clone());
                 }
                 group @ LayerGroupOrReference::Reference(_) => {
                     panic!("Unexpected group reference: {group:?}")
                 }
             }
         }
-        Ok(())
     }
 
-    fn add_layer(&mut self, layer: Layer) -> anyhow::Result<()> {
-        if self.known_layers.contains_key(&layer.id) {
-            return Err(anyhow!("layer \"{}\" is defined multiple times", layer.id));
+    fn add_layer(&mut self, layer: Layer) {
+        if self.check_unique_id(&layer.id, "layer", layer.origin.as_deref()) {
+            self.known_layers.insert(layer.id.clone(), layer);
         }
-        self.known_layers.insert(layer.id.clone(), layer.clone());
-        Ok(())
+    }
+
+    /// Checks that no other layer or group is defined with `id`.
+    /// If one is, the conflict is reported, naming where both of them are defined.
+    fn check_unique_id(&mut self, id: &str, kind: &str, origin: Option<&Origin>) -> bool {
+        let existing = if let Some(layer) = self.known_layers.get(id) {
+            ("layer", layer.origin.as_deref())
+        } else if let Some(group) = self.known_groups.get(id) {
+            ("group", group.origin.as_deref())
+        } else if let Some(group) = self.config.groups.iter().find_map(|group| match group {
+            LayerGroupOrReference::Definition(group) if group.id == id => Some(group),
+            _ => None,
+        }) {
+            ("group", group.origin.as_deref())
+        } else {
+            return true;
+        };
+        let (existing_kind, existing) = (existing.0, existing.1.cloned());
+        let existing = existing.unwrap_or_else(|| self.origin());
+        let origin = origin.cloned().unwrap_or_else(|| self.origin());
+        let message = if existing_kind != kind {
+            format!(
+                "Id \"{id}\" is used by both the {existing_kind} at {existing} and the {kind} at {origin}."
+            )
+        } else if existing == origin {
+            format!("The {kind} \"{id}\" at {origin} is included multiple times.")
+        } else {
+            format!("The {kind} \"{id}\" is defined multiple times, at {existing} and at {origin}.")
+        };
+        self.diagnostics
+            .push(origin.diagnostic(DiagnosticKind::DuplicateId, message));
+        false
     }
 }
 
 impl LayerConfig {
-    pub fn parse(layers_file_path: &Path) -> anyhow::Result<Self> {
-        let config = Self::default()
-            .parse_inclusion(layers_file_path)
-            .map_err(|err| anyhow!("Failed to resolve layers: {err}"))?;
-        for layer in &config.layers {
-            if layer.use_count == 0 {
-                tracing::warn!("Layer \"{}\" is unused.", layer.id)
+    /// Generates the JSON Schema of layer config files.
+    ///
+    /// The schema follows draft-07, as it's the draft best supported by editors.
+    pub fn json_schema() -> serde_json::Value {
+        SchemaSettings::draft07()
+            .into_generator()
+            .into_root_schema_for::<Self>()
+            .to_value()
+    }
+
+    /// Reads and parses the config at `location`, along with all configs it includes.
+    /// Fails if any of them contains an error.
+    pub async fn load(location: &LayerLocation, loader: &LayerLoader) -> anyhow::Result<Self> {
+        let (config, report) = Self::validate(location, loader).await;
+        let errors: Vec<String> = report
+            .with_severity(Severity::Error)
+            .map(|it| it.to_string())
+            .collect();
+        match config {
+            Some(config) if errors.is_empty() => {
+                for warning in report.with_severity(Severity::Warning) {
+                    tracing::warn!("{warning}")
+                }
+                Ok(config)
             }
+            _ => Err(anyhow!("Failed to resolve layers:\n{}", errors.join("\n"))),
         }
-        Ok(config)
     }
 
-    fn parse_inclusion(&self, layers_file_path: &Path) -> anyhow::Result<Self> {
-        let layers_file_path_with_ext = format!("{}.json5", layers_file_path.display());
-        let layers_file_path = if layers_file_path.to_str().unwrap().ends_with(".json5") {
-            layers_file_path
-        } else {
-            Path::new(&layers_file_path_with_ext)
+    /// Reads and parses the config at `location`, along with all configs it includes,
+    /// collecting every issue found within them instead of stopping at the first error.
+    ///
+    /// The config is returned as long as its own file can be read,
+    /// but is incomplete if any errors have been reported.
+    pub async fn validate(
+        location: &LayerLocation,
+        loader: &LayerLoader,
+    ) -> (Option<Self>, LayerReport) {
+        let documents = loader.read_all(location).await;
+        let mut diagnostics = Vec::new();
+        let config = Self::default().parse_inclusion(
+            location,
+            &documents,
+            &mut Vec::new(),
+            &mut diagnostics,
+        );
+        for layer in config.iter().flat_map(|it| &it.layers) {
+            if let (0, Some(origin)) = (layer.use_count, &layer.origin) {
+                diagnostics.push(origin.diagnostic(
+                    DiagnosticKind::UnusedLayer,
+                    format!("Layer \"{}\" is unused.", layer.id),
+                ));
+            }
+        }
+        (config, LayerReport { diagnostics })
+    }
+
+    /// Parses the config at `location`, which is included by the configs in `chain`.
+    fn parse_inclusion(
+        &self,
+        location: &LayerLocation,
+        documents: &LayerDocuments,
+        chain: &mut Vec<LayerLocation>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Option<Self> {
+        let root = Origin {
+            location: location.clone(),
+            line: None,
+            path: "$".to_owned(),
+        };
+        let layers_text = match documents.get(location) {
+            Some(Ok(text)) => text,
+            Some(Err(err)) => {
+                diagnostics.push(root.diagnostic(DiagnosticKind::InvalidFile, err));
+                return None;
+            }
+            None => {
+                let message = format!("Failed to read \"{location}\"");
+                diagnostics.push(root.diagnostic(DiagnosticKind::InvalidFile, message));
+                return None;
+            }
         };
-        let layers_text = std::fs::read_to_string(layers_file_path)
-            .map_err(|err| anyhow!("Failed to read \"{}\": {err}", layers_file_path.display()))?;
 
-        let mut config: Self = json5::from_str(&layers_text).map_err(|err| {
-            anyhow!(
-                "Invalid layer config at \"{}\": {err}",
-                layers_file_path.display()
-            )
-        })?;
+        let lines = LineIndex::new(layers_text);
+        let mut config = Entries {
+            location,
+            lines: &lines,
+            diagnostics,
+            invalid_ids: BTreeSet::new(),
+        }
+        .deserialize(layers_text)?;
 
         config
             .order_of_properties
@@ -131,10 +272,20 @@ impl LayerConfig {
         config.voxel_mappings.extend(self.voxel_mappings.clone());
         config.tiff_displays.extend(self.tiff_displays.clone());
 
-        config.parse_as_root(layers_file_path)
+        chain.push(location.clone());
+        let config = config.parse_as_root(location, documents, lines, chain, diagnostics);
+        chain.pop();
+        Some(config)
     }
 
-    fn parse_as_root(mut self, file_path: &Path) -> anyhow::Result<Self> {
+    fn parse_as_root(
+        mut self,
+        location: &LayerLocation,
+        documents: &LayerDocuments,
+        lines: LineIndex,
+        chain: &mut Vec<LayerLocation>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Self {
         let result = Self {
             include: vec![],
             layers: vec![],
@@ -142,64 +293,439 @@ impl LayerConfig {
             order_of_properties: std::mem::take(&mut self.order_of_properties),
             voxel_mappings: std::mem::take(&mut self.voxel_mappings),
             tiff_displays: Default::default(),
+            sources: vec![location.clone()],
+            invalid_ids: std::mem::take(&mut self.invalid_ids),
         };
 
         let mut context = ParseContext {
-            display: format!("{}", file_path.display()),
+            location: location.clone(),
+            lines,
+            path: "$".to_owned(),
+            diagnostics,
             config: result,
             known_layers: Default::default(),
             known_groups: Default::default(),
         };
 
-        for inclusion in self.include {
-            let resolved_path = file_path
-                .parent()
-                .unwrap_or_else(|| Path::new("/"))
-                .join(&inclusion);
-            let file = context.config.parse_inclusion(&resolved_path)?;
-            context.merge(file)?;
+        for (i, inclusion) in self.include.iter().enumerate() {
+            context.path = join_index("$.include", i);
+            let resolved = match location.resolve(inclusion) {
+                Ok(resolved) => resolved,
+                Err(err) => {
+                    context.report(DiagnosticKind::InvalidEntry, err);
+                    continue;
+                }
+            };
+            if chain.contains(&resolved) {
+                let cycle: Vec<String> = chain
+                    .iter()
+                    .chain([&resolved])
+                    .map(|it| format!("\"{it}\""))
+                    .collect();
+                context.report(
+                    DiagnosticKind::IncludeCycle,
+                    format!("Include cycle: {}", cycle.join(" -> ")),
+                );
+                continue;
+            }
+            if let Some(Err(err)) = documents.get(&resolved) {
+                context.report(DiagnosticKind::InvalidFile, err);
+                continue;
+            }
+            let Some(mut file) =
+                context
+                    .config
+                    .parse_inclusion(&resolved, documents, chain, context.diagnostics)
+            else {
+                continue;
+            };
+            context.config.sources.append(&mut file.sources);
+            context.merge(file);
         }
 
         for (key, band) in std::mem::take(&mut self.tiff_displays) {
-            let band = band.parse(&mut context)?;
-            context.config.tiff_displays.insert(key, band);
+            context.path = join_key("$.tiff_displays", &key);
+            match band.parse(&mut context) {
+                Ok(band) => {
+                    context.config.tiff_displays.insert(key, band);
+                }
+                Err(err) => context.report(DiagnosticKind::InvalidEntry, err),
+            }
         }
 
         for layer in self.layers {
-            let layer = layer.parse(&mut context)?;
-            context.add_layer(layer)?;
+            context.path = layer
+                .origin
+                .as_ref()
+                .map_or_else(String::new, |it| it.path.clone());
+            match layer.clone().parse(&mut context) {
+                Ok(layer) => context.add_layer(layer),
+                Err(err) => {
+                    context.report(DiagnosticKind::InvalidEntry, err);
+                    // Keep the layer, so that references to it don't cause further errors.
+                    context.add_layer(layer);
+                }
+            }
         }
 
         for group in self.groups {
-            let group = group.parse(&mut context)?;
-            context.config.groups.push(group);
+            context.path = group
+                .origin()
+                .map_or_else(String::new, |it| it.path.clone());
+            if matches!(&group, LayerGroupOrReference::Reference(it) if context.config.invalid_ids.contains(&it.id))
+            {
+                continue;
+            }
+            let is_definition = matches!(group, LayerGroupOrReference::Definition(_));
+            match group.parse(&mut context) {
+                Ok(LayerGroupOrReference::Definition(group))
+                    if is_definition
+                        && !context.check_unique_id(
+                            &group.id,
+                            "group",
+                            group.origin.as_deref(),
+                        ) => {}
+                Ok(group) => context.config.groups.push(group),
+                Err(err) => context.report(DiagnosticKind::InvalidEntry, err),
+            }
         }
 
-        for (key, display) in &context.config.tiff_displays {
-            if display.use_count == 0 {
-                tracing::warn!("[{}] TIFF display \"{key}\" is unused.", context.display)
-            }
+        let unused_displays: Vec<String> = context
+            .config
+            .tiff_displays
+            .iter()
+            .filter(|(_, display)| display.use_count == 0)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in unused_displays {
+            context.path = join_key("$.tiff_displays", &key);
+            context.report(
+                DiagnosticKind::UnusedTiffDisplay,
+                format!("TIFF display \"{key}\" is unused."),
+            );
         }
 
-        for (key, mapping) in &context.config.voxel_mappings {
-            let use_count = match mapping {
-                VoxelMappingDefinition::Range(it) => it.use_count,
-                VoxelMappingDefinition::Category(it) => it.use_count,
-            };
-            if use_count == 0 {
-                tracing::warn!("[{}] Voxel mapping \"{key}\" is unused.", context.display)
-            }
+        let unused_mappings: Vec<String> = context
+            .config
+            .voxel_mappings
+            .iter()
+            .filter(|(_, mapping)| match mapping {
+                VoxelMappingDefinition::Range(it) => it.use_count == 0,
+                VoxelMappingDefinition::Category(it) => it.use_count == 0,
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in unused_mappings {
+            context.path = join_key("$.voxel_mappings", &key);
+            context.report(
+                DiagnosticKind::UnusedVoxelMapping,
+                format!("Voxel mapping \"{key}\" is unused."),
+            );
         }
 
-        for (key, group) in context.known_groups {
+        for (key, group) in &context.known_groups {
             if group.use_count == 0 {
-                tracing::warn!("[{}] Group \"{key}\" is unused.", context.display)
+                let diagnostic = match &group.origin {
+                    Some(origin) => (**origin).clone(),
+                    None => context.origin(),
+                }
+                .diagnostic(
+                    DiagnosticKind::UnusedGroup,
+                    format!("Group \"{key}\" is unused."),
+                );
+                context.diagnostics.push(diagnostic);
             }
         }
         context
             .config
             .layers
             .extend(&mut context.known_layers.into_values());
-        Ok(context.config)
+        context.config
+    }
+}
+
+/// Deserializes the entries of a config file one by one,
+/// so that every entry not matching the config's format is reported.
+struct Entries<'a> {
+    location: &'a LayerLocation,
+    lines: &'a LineIndex,
+    diagnostics: &'a mut Vec<Diagnostic>,
+
+    /// The ids of the list items that could not be deserialized.
+    invalid_ids: BTreeSet<String>,
+}
+
+impl Entries<'_> {
+    /// Deserializes a config file.
+    /// Invalid entries are reported and left out of the returned config.
+    fn deserialize(&mut self, text: &str) -> Option<LayerConfig> {
+        let value: Value = match json5::from_str(text) {
+            Ok(value) => value,
+            Err(json5::Error::Message { msg, location }) => {
+                // Syntax errors end with a line describing what was expected.
+                let message = msg.lines().last().unwrap_or_default();
+                let message = message.trim_start_matches([' ', '=']);
+                let origin = Origin {
+                    line: location.map(|it| it.line),
+                    ..self.origin("$".to_owned())
+                };
+                self.diagnostics
+                    .push(origin.diagnostic(DiagnosticKind::InvalidFile, message));
+                return None;
+            }
+        };
+        let Value::Object(mut fields) = value else {
+            self.report("$".to_owned(), "expected an object");
+            return None;
+        };
+        let layers = fields.remove("layers");
+        let groups = fields.remove("groups");
+        let tiff_displays = fields.remove("tiff_displays");
+        let voxel_mappings = fields.remove("voxel_mappings");
+
+        let mut config: LayerConfig = self.value(Value::Object(fields), "$")?;
+        config.layers = self
+            .list(layers, "$.layers")
+            .into_iter()
+            .map(|(origin, mut layer): (Origin, Layer)| {
+                layer.origin = Some(Box::new(origin));
+                layer
+            })
+            .collect();
+        config.groups = self
+            .list(groups, "$.groups")
+            .into_iter()
+            .map(|(origin, mut group)| {
+                let origin = Some(Box::new(origin));
+                match &mut group {
+                    LayerGroupOrReference::Definition(it) => it.origin = origin,
+                    LayerGroupOrReference::Reference(it) => it.origin = origin,
+                }
+                group
+            })
+            .collect();
+        config.tiff_displays = self.map(tiff_displays, "$.tiff_displays");
+        config.voxel_mappings = self.map(voxel_mappings, "$.voxel_mappings");
+        config.invalid_ids = std::mem::take(&mut self.invalid_ids);
+        Some(config)
+    }
+
+    fn origin(&self, path: String) -> Origin {
+        Origin {
+            location: self.location.clone(),
+            line: self.lines.line(&path),
+            path,
+        }
+    }
+
+    fn report(&mut self, path: String, message: impl Display) {
+        let diagnostic = self
+            .origin(path)
+            .diagnostic(DiagnosticKind::InvalidEntry, message);
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn value<T: DeserializeOwned>(&mut self, value: Value, path: &str) -> Option<T> {
+        serde_path_to_error::deserialize(value)
+            .map_err(|err| {
+                let mut path = path.to_owned();
+                for segment in err.path() {
+                    path = match segment {
+                        Segment::Seq { index } => join_index(&path, *index),
+                        Segment::Map { key } => join_key(&path, key),
+                        _ => path,
+                    };
+                }
+                self.report(path, err.inner());
+            })
+            .ok()
+    }
+
+    /// Deserializes the items of a list.
+    /// The ids of invalid items are remembered, so that references to them aren't reported again.
+    fn list<T: DeserializeOwned>(&mut self, value: Option<Value>, path: &str) -> Vec<(Origin, T)> {
+        let items = match value {
+            None => return vec![],
+            Some(Value::Array(items)) => items,
+            Some(_) => {
+                self.report(path.to_owned(), "expected an array");
+                return vec![];
+            }
+        };
+        let mut entries = Vec::with_capacity(items.len());
+        for (i, item) in items.into_iter().enumerate() {
+            let path = join_index(path, i);
+            let id = item.get("id").and_then(Value::as_str).map(str::to_owned);
+            match self.value(item, &path) {
+                Some(entry) => entries.push((self.origin(path), entry)),
+                None => self.invalid_ids.extend(id),
+            }
+        }
+        entries
+    }
+
+    fn map<T: DeserializeOwned>(&mut self, value: Option<Value>, path: &str) -> HashMap<String, T> {
+        let entries = match value {
+            None => return HashMap::new(),
+            Some(Value::Object(entries)) => entries,
+            Some(_) => {
+                self.report(path.to_owned(), "expected an object");
+                return HashMap::new();
+            }
+        };
+        let mut result = HashMap::with_capacity(entries.len());
+        for (key, entry) in entries {
+            if let Some(entry) = self.value(entry, &join_key(path, &key)) {
+                result.insert(key, entry);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses the first of `files` as the root config, and returns the issues found in all of them.
+    fn diagnostics(files: &[(&str, &str)]) -> Vec<Diagnostic> {
+        let location = |name: &str| LayerLocation::parse(&format!("/layers/{name}")).unwrap();
+        let documents: LayerDocuments = files
+            .iter()
+            .map(|(name, text)| (location(name), Ok(text.to_string())))
+            .collect();
+        let mut diagnostics = Vec::new();
+        LayerConfig::default().parse_inclusion(
+            &location(files[0].0),
+            &documents,
+            &mut Vec::new(),
+            &mut diagnostics,
+        );
+        diagnostics
+    }
+
+    #[test]
+    fn references_to_invalid_entries_are_not_reported() {
+        let diagnostics = diagnostics(&[
+            (
+                "root",
+                "{
+                    include: ['layers'],
+                    groups: [{ id: 'root', children: ['broken', { id: 'invalid' }] }],
+                }",
+            ),
+            (
+                "layers",
+                "{
+                    layers: [{ id: 'broken', type: 'Unknown' }],
+                    groups: [{ id: 'invalid', children: 'none' }],
+                }",
+            ),
+        ]);
+        let kinds: Vec<_> = diagnostics
+            .iter()
+            .map(|it| (it.kind, it.path.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (DiagnosticKind::InvalidEntry, "$.layers[0]"),
+                (DiagnosticKind::InvalidEntry, "$.groups[0]"),
+            ]
+        );
+    }
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<(DiagnosticKind, &str)> {
+        diagnostics
+            .iter()
+            .map(|it| (it.kind, it.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn include_cycles_are_reported_with_their_chain() {
+        let direct = diagnostics(&[("a", "{ include: ['b'] }"), ("b", "{ include: ['a'] }")]);
+        assert_eq!(
+            messages(&direct),
+            [(
+                DiagnosticKind::IncludeCycle,
+                "Include cycle: \"/layers/a.json5\" -> \"/layers/b.json5\" -> \"/layers/a.json5\""
+            )]
+        );
+        assert_eq!(direct[0].file.to_string(), "/layers/b.json5");
+        assert_eq!(direct[0].path, "$.include[0]");
+
+        let relative = diagnostics(&[
+            ("a", "{ include: ['sub/b'] }"),
+            ("sub/b", "{ include: ['./../a'] }"),
+        ]);
+        assert_eq!(
+            messages(&relative),
+            [(
+                DiagnosticKind::IncludeCycle,
+                "Include cycle: \"/layers/a.json5\" -> \"/layers/sub/b.json5\" -> \"/layers/a.json5\""
+            )]
+        );
+    }
+
+    #[test]
+    fn diamond_includes_are_reported_once() {
+        let diagnostics = diagnostics(&[
+            (
+                "root",
+                "{ include: ['b', 'c'], groups: [{ id: 'root', children: ['x'] }] }",
+            ),
+            ("b", "{ include: ['d'] }"),
+            ("c", "{ include: ['d'] }"),
+            (
+                "d",
+                "{\n  layers: [\n    { id: 'x', type: 'Wmts' },\n  ],\n}",
+            ),
+        ]);
+        assert_eq!(
+            messages(&diagnostics),
+            [(
+                DiagnosticKind::DuplicateId,
+                "The layer \"x\" at \"/layers/d.json5:3\" is included multiple times."
+            )]
+        );
+        assert_eq!(diagnostics[0].file.to_string(), "/layers/d.json5");
+    }
+
+    #[test]
+    fn ids_are_unique_across_layers_and_groups() {
+        let diagnostics = diagnostics(&[(
+            "root",
+            "{\n  layers: [\n    { id: 'x', type: 'Wmts' },\n  ],\n  groups: [\n    { id: 'x', children: ['x'] },\n  ],\n}",
+        )]);
+        assert_eq!(
+            messages(&diagnostics),
+            [(
+                DiagnosticKind::DuplicateId,
+                "Id \"x\" is used by both the layer at \"/layers/root.json5:3\" \
+                 and the group at \"/layers/root.json5:6\"."
+            )]
+        );
+    }
+
+    #[test]
+    fn duplicates_across_files_name_both_files() {
+        let diagnostics = diagnostics(&[
+            (
+                "root",
+                "{ include: ['b', 'c'], groups: [{ id: 'root', children: ['x'] }] }",
+            ),
+            ("b", "{\n  layers: [{ id: 'x', type: 'Wmts' }],\n}"),
+            ("c", "{\n\n  layers: [{ id: 'x', type: 'Wmts' }],\n}"),
+        ]);
+        assert_eq!(
+            messages(&diagnostics),
+            [(
+                DiagnosticKind::DuplicateId,
+                "The layer \"x\" is defined multiple times, \
+                 at \"/layers/b.json5:2\" and at \"/layers/c.json5:3\"."
+            )]
+        );
+        assert_eq!(diagnostics[0].file.to_string(), "/layers/c.json5");
     }
 }
@@ -1,8 +1,9 @@
 use crate::LayerSource;
 use crate::OgcSource;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all(serialize = "camelCase"))]
 pub struct EarthquakesLayer {
     /// The layer's source, defining where the layer is loaded from.
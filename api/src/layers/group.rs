@@ -1,10 +1,13 @@
 use crate::layers::access::LayerAccess;
 use crate::layers::config::{Parse, ParseContext};
+use crate::layers::report::{DiagnosticKind, Origin};
 use anyhow::anyhow;
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
 use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all(serialize = "camelCase"))]
 pub struct LayerGroup {
     /// A unique identifier for the group. Will also be used as part of the translation key for the group's display name.
@@ -25,9 +28,14 @@ pub struct LayerGroup {
     /// If left out, the layer is publicly available.
     #[serde(default, skip_serializing)]
     pub access: Option<LayerAccess>,
+
+    /// Where this group has been defined.
+    /// This is only known for top level groups.
+    #[serde(skip)]
+    pub origin: Option<Box<Origin>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum LayerGroupChild {
     /// A specific layer's id.
@@ -47,10 +55,14 @@ pub enum LayerGroupOrReference {
     Reference(LayerGroupReference),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all(serialize = "camelCase"))]
 pub struct LayerGroupReference {
     pub id: String,
+
+    /// Where this reference is located.
+    #[serde(skip)]
+    pub origin: Option<Box<Origin>>,
 }
 
 impl<'de> Deserialize<'de> for LayerGroupOrReference {
@@ -82,24 +94,59 @@ impl Serialize for LayerGroupOrReference {
     }
 }
 
+impl JsonSchema for LayerGroupOrReference {
+    fn schema_name() -> Cow<'static, str> {
+        "LayerGroupOrReference".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "anyOf": [
+                generator.subschema_for::<LayerGroupReference>(),
+                generator.subschema_for::<LayerGroup>(),
+            ]
+        })
+    }
+}
+
+impl LayerGroupOrReference {
+    /// Where this group or reference is located.
+    /// This is only known for top level groups.
+    pub fn origin(&self) -> Option<&Origin> {
+        match self {
+            LayerGroupOrReference::Definition(group) => group.origin.as_deref(),
+            LayerGroupOrReference::Reference(reference) => reference.origin.as_deref(),
+        }
+    }
+}
+
 impl Parse for LayerGroup {
+    /// Parses the group's children.
+    /// Invalid children are reported and left out, so that the remaining ones are still checked.
     fn parse(mut self, context: &mut ParseContext) -> anyhow::Result<Self> {
-        for child in std::mem::take(&mut self.children) {
+        let path = context.path.clone();
+        for (i, child) in std::mem::take(&mut self.children).into_iter().enumerate() {
+            context.path = format!("{path}.children[{i}]");
             match child {
-                LayerGroupChild::Layer(id) => {
-                    let layer = context
-                        .known_layers
-                        .get_mut(&id)
-                        .ok_or_else(|| anyhow!("Unknown layer: {id}"))?;
-                    layer.use_count += 1;
-                    self.children.push(LayerGroupChild::Layer(id));
-                }
-                LayerGroupChild::Group(group) => {
-                    self.children
-                        .push(LayerGroupChild::Group(group.parse(context)?));
-                }
+                LayerGroupChild::Layer(id) => match context.known_layers.get_mut(&id) {
+                    Some(layer) => {
+                        layer.use_count += 1;
+                        self.children.push(LayerGroupChild::Layer(id));
+                    }
+                    None if context.config.invalid_ids.contains(&id) => {}
+                    None => {
+                        context.report(DiagnosticKind::InvalidEntry, format!("Unknown layer: {id}"))
+                    }
+                },
+                LayerGroupChild::Group(LayerGroupOrReference::Reference(reference))
+                    if context.config.invalid_ids.contains(&reference.id) => {}
+                LayerGroupChild::Group(group) => match group.parse(context) {
+                    Ok(group) => self.children.push(LayerGroupChild::Group(group)),
+                    Err(err) => context.report(DiagnosticKind::InvalidEntry, err),
+                },
             }
         }
+        context.path = path;
         Ok(self)
     }
 }
@@ -111,9 +158,10 @@ impl Parse for LayerGroupOrReference {
                 Ok(LayerGroupOrReference::Definition(group.parse(context)?))
             }
             LayerGroupOrReference::Reference(reference) => {
-                let group = context.known_groups.get_mut(&reference.id).ok_or_else(|| {
-                    anyhow!("[{}] Unknown group: {}", context.display, reference.id)
-                })?;
+                let group = context
+                    .known_groups
+                    .get_mut(&reference.id)
+                    .ok_or_else(|| anyhow!("Unknown group: {}", reference.id))?;
                 group.use_count += 1;
                 Ok(LayerGroupOrReference::Definition(group.clone()))
             }
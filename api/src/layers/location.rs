@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, anyhow};
+use aws_sdk_s3::Client;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize, Serializer};
+use url::Url;
+
+/// The file extension that is added to locations lacking it.
+const EXTENSION: &str = ".json5";
+
+/// The location of a layer config file.
+///
+/// Locations are either paths on the local filesystem, `s3://bucket/key` or `https://` URLs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LayerLocation {
+    File(PathBuf),
+    S3 { bucket: String, key: String },
+    Http(Url),
+}
+
+/// The text of layer config files, or the error encountered when reading them, by their location.
+pub(in crate::layers) type LayerDocuments = HashMap<LayerLocation, anyhow::Result<String>>;
+
+impl LayerLocation {
+    /// Parses a location, adding the `.json5` extension if it's missing.
+    pub fn parse(location: &str) -> anyhow::Result<Self> {
+        let location = if location.ends_with(EXTENSION) {
+            location.to_owned()
+        } else {
+            format!("{location}{EXTENSION}")
+        };
+        if let Some(path) = location.strip_prefix("s3://") {
+            let (bucket, key) = path
+                .split_once('/')
+                .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+                .ok_or_else(|| anyhow!("Invalid S3 location \"{location}\""))?;
+            return Ok(Self::S3 {
+                bucket: bucket.to_owned(),
+                key: normalize(key),
+            });
+        }
+        if location.starts_with("http://") {
+            return Err(anyhow!(
+                "Insecure location \"{location}\", only https:// URLs are supported"
+            ));
+        }
+        if location.starts_with("https://") {
+            let url =
+                Url::parse(&location).with_context(|| format!("Invalid URL \"{location}\""))?;
+            return Ok(Self::Http(url));
+        }
+        Ok(Self::File(PathBuf::from(location)))
+    }
+
+    /// Resolves an `include` entry of the config at this location.
+    /// Relative entries are resolved against this location, absolute ones are used as they are.
+    pub fn resolve(&self, inclusion: &str) -> anyhow::Result<Self> {
+        if inclusion.contains("://") {
+            return Self::parse(inclusion);
+        }
+        match self {
+            Self::File(path) => {
+                let parent = path.parent().unwrap_or_else(|| Path::new("/"));
+                Self::parse(&normalize_path(&parent.join(inclusion)).to_string_lossy())
+            }
+            Self::S3 { bucket, key } => {
+                let key = match key.rsplit_once('/') {
+                    Some((parent, _)) if !inclusion.starts_with('/') => {
+                        format!("{parent}/{inclusion}")
+                    }
+                    _ => inclusion.to_owned(),
+                };
+                Self::parse(&format!("s3://{bucket}/{}", normalize(&key)))
+            }
+            Self::Http(url) => {
+                let url = url
+                    .join(inclusion)
+                    .with_context(|| format!("Invalid inclusion \"{inclusion}\" in \"{self}\""))?;
+                Self::parse(url.as_str())
+            }
+        }
+    }
+}
+
+/// Removes empty, `.` and `..` segments from a slash-separated key.
+fn normalize(key: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in key.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Removes `.` and `..` segments from a path, so that each file has a single location.
+/// Leading `..` segments of relative paths are kept, as there is nothing to remove them from.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match result.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    result.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => result.push(".."),
+            },
+            component => result.push(component),
+        }
+    }
+    result
+}
+
+impl fmt::Display for LayerLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::S3 { bucket, key } => write!(f, "s3://{bucket}/{key}"),
+            Self::Http(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+impl Serialize for LayerLocation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// The part of a layer config that is needed to find the files it includes.
+#[derive(Deserialize)]
+struct Inclusions {
+    #[serde(default)]
+    include: Vec<String>,
+}
+
+/// Reads layer config files from their locations.
+#[derive(Clone)]
+pub struct LayerLoader {
+    s3: Client,
+    http: reqwest::Client,
+}
+
+impl LayerLoader {
+    /// Creates a loader reading files from S3 with the given client.
+    pub fn new(s3: Client) -> Self {
+        Self {
+            s3,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Reads the file at `root`, along with all files it includes, directly or indirectly.
+    pub(in crate::layers) async fn read_all(&self, root: &LayerLocation) -> LayerDocuments {
+        let mut documents = LayerDocuments::new();
+        let mut pending = vec![root.clone()];
+        while let Some(location) = pending.pop() {
+            if documents.contains_key(&location) {
+                continue;
+            }
+            let text = self.read(&location).await;
+            // Unreadable files, invalid files and invalid inclusions are reported once they're parsed.
+            let inclusions = text
+                .as_ref()
+                .ok()
+                .and_then(|text| json5::from_str::<Inclusions>(text).ok());
+            for inclusion in inclusions.map(|it| it.include).unwrap_or_default() {
+                if let Ok(inclusion) = location.resolve(&inclusion) {
+                    pending.push(inclusion);
+                }
+            }
+            documents.insert(location, text);
+        }
+        documents
+    }
+
+    /// Reads the file at a location.
+    pub async fn read(&self, location: &LayerLocation) -> anyhow::Result<String> {
+        match location {
+            LayerLocation::File(path) => tokio::fs::read_to_string(path)
+                .await
+                .map_err(|err| anyhow!("Failed to read \"{location}\": {err}")),
+            LayerLocation::S3 { bucket, key } => {
+                let object = self
+                    .s3
+                    .get_object()
+                    .bucket(bucket)
+                    .key(key)
+                    .send()
+                    .await
+                    .map_err(|err| match err.into_service_error() {
+                        err if err.is_no_such_key() => anyhow!("\"{location}\" does not exist"),
+                        err => anyhow!("Failed to read \"{location}\": {err}"),
+                    })?;
+                let bytes = object
+                    .body
+                    .collect()
+                    .await
+                    .map_err(|err| anyhow!("Failed to read \"{location}\": {err}"))?
+                    .into_bytes();
+                String::from_utf8(bytes.to_vec())
+                    .map_err(|_| anyhow!("\"{location}\" is not valid UTF-8"))
+            }
+            LayerLocation::Http(url) => {
+                let response = self
+                    .http
+                    .get(url.clone())
+                    .send()
+                    .await
+                    .map_err(|err| anyhow!("Failed to read \"{location}\": {err}"))?;
+                match response.status() {
+                    StatusCode::NOT_FOUND => Err(anyhow!("\"{location}\" does not exist")),
+                    status if !status.is_success() => {
+                        Err(anyhow!("Failed to read \"{location}\": HTTP {status}"))
+                    }
+                    _ => response
+                        .text()
+                        .await
+                        .map_err(|err| anyhow!("Failed to read \"{location}\": {err}")),
+                }
+            }
+        }
+    }
+
+    /// Determines the current version of the file at a location,
+    /// which changes whenever the file is modified.
+    /// Returns `None` if the version can't be determined.
+    pub async fn version(&self, location: &LayerLocation) -> Option<String> {
+        match location {
+            LayerLocation::File(path) => {
+                let modified = tokio::fs::metadata(path).await.ok()?.modified().ok()?;
+                Some(format!("{modified:?}"))
+            }
+            LayerLocation::S3 { bucket, key } => {
+                let object = self
+                    .s3
+                    .head_object()
+                    .bucket(bucket)
+                    .key(key)
+                    .send()
+                    .await
+                    .ok()?;
+                object.e_tag().map(str::to_owned)
+            }
+            LayerLocation::Http(url) => {
+                let response = self.http.head(url.clone()).send().await.ok()?;
+                let headers = response.headers();
+                headers
+                    .get(reqwest::header::ETAG)
+                    .or_else(|| headers.get(reqwest::header::LAST_MODIFIED))
+                    .and_then(|it| it.to_str().ok())
+                    .map(str::to_owned)
+            }
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use crate::data::TranslatedString;
 use crate::layers::config::{Parse, ParseContext};
+use schemars::{JsonSchema, Schema};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::collections::HashMap;
 
 mod config;
@@ -42,7 +44,18 @@ pub use access::*;
 mod opacity;
 pub use opacity::*;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+mod location;
+pub use location::{LayerLoader, LayerLocation};
+
+mod reload;
+pub use reload::LiveLayerConfig;
+
+mod report;
+pub use report::{
+    Diagnostic, DiagnosticKind, LayerReport, LayerValidation, Origin, ReportFormat, Severity,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all(serialize = "camelCase"))]
 pub struct Layer {
     /// A unique identifier for the layer. Will also be used as part of the translation key for the layer's display name.
@@ -85,9 +98,13 @@ pub struct Layer {
     /// This is used to ensure that the layer is not unused.
     #[serde(skip, default)]
     pub use_count: u32,
+
+    /// Where this layer has been defined.
+    #[serde(skip)]
+    pub origin: Option<Box<Origin>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "source")]
 pub enum InfoBox {
     /// The legend is fetched as HTML from api3.geo.admin.ch via the layer's id.
@@ -114,28 +131,52 @@ pub enum InfoBox {
 
 /// Information entries displayed in the info box.
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+#[schemars(transform = add_label_key_alias)]
 pub struct InformationEntry {
     #[serde(alias = "key")]
     pub label_key: String,
     pub value: InformationValue,
 }
 
+/// Adds the `key` alias of [InformationEntry::label_key] to its schema,
+/// as aliases are not part of generated schemas.
+fn add_label_key_alias(schema: &mut Schema) {
+    let Some(label_key) = schema
+        .get("properties")
+        .and_then(|it| it.get("label_key"))
+        .cloned()
+    else {
+        return;
+    };
+    if let Some(properties) = schema
+        .get_mut("properties")
+        .and_then(|it| it.as_object_mut())
+    {
+        properties.insert("key".to_owned(), label_key);
+    }
+    schema.insert("required".to_owned(), json!(["value"]));
+    schema.insert(
+        "oneOf".to_owned(),
+        json!([{ "required": ["label_key"] }, { "required": ["key"] }]),
+    );
+}
+
 /// A value in the info box's information table.
 ///
 /// - [`Text`](InformationValue::Text): A plain string.
 /// - [`Link`](InformationValue::Link): A `{ key, url }` object rendered as a link whose label is
 ///   the translation of `key`. Url can be a url which starts with `http://` or `https://`
 ///   or a translation key to resolve a localized url.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum InformationValue {
     Link { key: String, url: String },
     Text(String),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum LayerDetail {
     Wmts(WmtsLayer),
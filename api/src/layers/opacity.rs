@@ -1,6 +1,8 @@
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
 use serde::de::Unexpected;
 use serde::{Deserialize, Deserializer};
 use serde::{Serialize, Serializer};
+use std::borrow::Cow;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum LayerOpacity {
@@ -48,3 +50,16 @@ impl Serialize for LayerOpacity {
         }
     }
 }
+
+impl JsonSchema for LayerOpacity {
+    fn schema_name() -> Cow<'static, str> {
+        "LayerOpacity".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "The opacity, or \"Disabled\" if the opacity can't be changed.",
+            "anyOf": [{ "type": "number" }, { "const": "Disabled" }]
+        })
+    }
+}
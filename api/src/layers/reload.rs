@@ -0,0 +1,103 @@
+use std::sync::{Arc, RwLock, RwLockWriteGuard};
+use std::time::Duration;
+
+use crate::{LayerConfig, LayerLoader, LayerLocation};
+
+/// The layer config served by the API, which is reloaded whenever its files change.
+///
+/// A reloaded config only replaces the current one if it has been parsed successfully.
+#[derive(Clone)]
+pub struct LiveLayerConfig {
+    location: LayerLocation,
+    loader: LayerLoader,
+    current: Arc<RwLock<Loaded>>,
+}
+
+struct Loaded {
+    config: Arc<LayerConfig>,
+
+    /// The versions of the files from which `config` has been parsed,
+    /// as of the last time they were checked.
+    versions: Vec<Option<String>>,
+}
+
+impl LiveLayerConfig {
+    /// Serves `config`, which has been loaded from `location`.
+    pub async fn new(location: LayerLocation, loader: LayerLoader, config: LayerConfig) -> Self {
+        let loaded = Loaded {
+            versions: versions(&loader, &config.sources).await,
+            config: Arc::new(config),
+        };
+        Self {
+            location,
+            loader,
+            current: Arc::new(RwLock::new(loaded)),
+        }
+    }
+
+    /// The current config.
+    pub fn get(&self) -> Arc<LayerConfig> {
+        let loaded = self.current.read().unwrap_or_else(|e| e.into_inner());
+        loaded.config.clone()
+    }
+
+    /// Loads the config anew and replaces the current one with it.
+    /// If loading fails, the current config is kept and the error is returned.
+    pub async fn reload(&self) -> anyhow::Result<Arc<LayerConfig>> {
+        let config = Arc::new(LayerConfig::load(&self.location, &self.loader).await?);
+        let versions = versions(&self.loader, &config.sources).await;
+        let mut loaded = self.lock();
+        loaded.versions = versions;
+        loaded.config = config.clone();
+        tracing::info!(
+            "Reloaded {layers} layers in {groups} root groups.",
+            groups = config.groups.len(),
+            layers = config.layers.len()
+        );
+        Ok(config)
+    }
+
+    /// Reloads the config if any of its files have changed since they were last checked.
+    async fn reload_if_changed(&self) -> anyhow::Result<()> {
+        let config = self.get();
+        let current = versions(&self.loader, &config.sources).await;
+        {
+            let mut loaded = self.lock();
+            if !Arc::ptr_eq(&loaded.config, &config) || current == loaded.versions {
+                return Ok(());
+            }
+            // Remember the change even if reloading fails, so that the error is only reported once.
+            loaded.versions = current;
+        }
+        self.reload().await.map(|_| ())
+    }
+
+    /// Checks the config's files for changes at the given interval, reloading them when they do.
+    /// A zero interval disables the check, leaving only explicit reloads.
+    pub fn watch(self, interval: Duration) {
+        if interval.is_zero() {
+            return;
+        }
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                if let Err(err) = self.reload_if_changed().await {
+                    tracing::error!("Keeping the current layer config, as reloading failed: {err}")
+                }
+            }
+        });
+    }
+
+    fn lock(&self) -> RwLockWriteGuard<'_, Loaded> {
+        self.current.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+async fn versions(loader: &LayerLoader, sources: &[LayerLocation]) -> Vec<Option<String>> {
+    let mut versions = Vec::with_capacity(sources.len());
+    for source in sources {
+        versions.push(loader.version(source).await);
+    }
+    versions
+}
@@ -0,0 +1,600 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::LayerLocation;
+
+/// How severe a [Diagnostic] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => f.write_str("warning"),
+            Self::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// The kinds of issues that are found when validating a layer config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticKind {
+    InvalidFile,
+    InvalidEntry,
+    IncludeCycle,
+    DuplicateId,
+    UnusedLayer,
+    UnusedGroup,
+    UnusedTiffDisplay,
+    UnusedVoxelMapping,
+}
+
+impl DiagnosticKind {
+    /// The identifier of the kind, as used on the command line and in reports.
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::InvalidFile => "invalid-file",
+            Self::InvalidEntry => "invalid-entry",
+            Self::IncludeCycle => "include-cycle",
+            Self::DuplicateId => "duplicate-id",
+            Self::UnusedLayer => "unused-layer",
+            Self::UnusedGroup => "unused-group",
+            Self::UnusedTiffDisplay => "unused-tiff-display",
+            Self::UnusedVoxelMapping => "unused-voxel-mapping",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::InvalidFile => "A config file that can't be read or isn't valid JSON5.",
+            Self::InvalidEntry => {
+                "An entry that doesn't match the config's format or references something unknown."
+            }
+            Self::IncludeCycle => "A file that is included by one of the files it includes.",
+            Self::DuplicateId => "An id that is used by more than one layer or group.",
+            Self::UnusedLayer => "A layer that isn't part of any group.",
+            Self::UnusedGroup => "An included group that is never referenced.",
+            Self::UnusedTiffDisplay => "A TIFF display that no layer uses.",
+            Self::UnusedVoxelMapping => "A voxel mapping that no layer uses.",
+        }
+    }
+
+    fn severity(self) -> Severity {
+        match self {
+            Self::InvalidFile | Self::InvalidEntry | Self::IncludeCycle | Self::DuplicateId => {
+                Severity::Error
+            }
+            Self::UnusedLayer
+            | Self::UnusedGroup
+            | Self::UnusedTiffDisplay
+            | Self::UnusedVoxelMapping => Severity::Warning,
+        }
+    }
+}
+
+/// An issue found in a layer config.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+
+    /// The file containing the issue.
+    pub file: LayerLocation,
+
+    /// The one-based line at which the issue is located, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+
+    /// The JSON path of the entry containing the issue, e.g. `$.layers[3].source`.
+    pub path: String,
+
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}] {}", self.severity, self.kind.id(), self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        write!(f, " at {}: {}", self.path, self.message)
+    }
+}
+
+/// The location of an entry within a layer config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    pub location: LayerLocation,
+    pub line: Option<usize>,
+    pub path: String,
+}
+
+impl Origin {
+    pub(in crate::layers) fn diagnostic(
+        &self,
+        kind: DiagnosticKind,
+        message: impl fmt::Display,
+    ) -> Diagnostic {
+        Diagnostic {
+            severity: kind.severity(),
+            kind,
+            file: self.location.clone(),
+            line: self.line,
+            path: self.path.clone(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}", self.location)?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        write!(f, "\"")
+    }
+}
+
+/// The formats in which a [LayerReport] can be printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+    /// One line per diagnostic.
+    Text,
+    /// A JSON object listing the diagnostics.
+    Json,
+    /// A SARIF 2.1.0 log, as understood by code scanning tools.
+    Sarif,
+}
+
+/// The options of `--validate-only`.
+#[derive(clap::Parser, Debug, Clone)]
+pub struct LayerValidation {
+    /// The format in which the validation report is printed.
+    #[clap(long = "validation-format", value_enum, default_value = "text")]
+    pub format: ReportFormat,
+
+    /// The lowest severity that causes validation to fail.
+    #[clap(long = "validation-fail-on", value_enum, default_value = "error")]
+    pub fail_on: Severity,
+
+    /// Kinds of diagnostics that are treated as errors, e.g. `unused-layer`.
+    #[clap(long = "validation-deny", value_enum, value_delimiter = ',')]
+    pub deny: Vec<DiagnosticKind>,
+}
+
+impl LayerValidation {
+    /// Prints `report` and determines whether validation has failed.
+    pub fn conclude(&self, mut report: LayerReport) -> bool {
+        report.deny(&self.deny);
+        println!("{}", report.render(self.format));
+        !report.fails(self.fail_on)
+    }
+}
+
+/// The issues found when validating a layer config and the files it includes.
+#[derive(Debug, Default, Serialize)]
+pub struct LayerReport {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl LayerReport {
+    /// Raises the diagnostics of the given kinds to errors.
+    pub fn deny(&mut self, kinds: &[DiagnosticKind]) {
+        for diagnostic in &mut self.diagnostics {
+            if kinds.contains(&diagnostic.kind) {
+                diagnostic.severity = Severity::Error;
+            }
+        }
+    }
+
+    /// The diagnostics of the given severity.
+    pub fn with_severity(&self, severity: Severity) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(move |it| it.severity == severity)
+    }
+
+    /// Whether any diagnostic is at least as severe as `threshold`.
+    pub fn fails(&self, threshold: Severity) -> bool {
+        self.diagnostics.iter().any(|it| it.severity >= threshold)
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Text => self.render_text(),
+            ReportFormat::Json => {
+                let report = json!({
+                    "errors": self.with_severity(Severity::Error).count(),
+                    "warnings": self.with_severity(Severity::Warning).count(),
+                    "diagnostics": self.diagnostics,
+                });
+                serde_json::to_string_pretty(&report).unwrap_or_default()
+            }
+            ReportFormat::Sarif => serde_json::to_string_pretty(&self.sarif()).unwrap_or_default(),
+        }
+    }
+
+    fn render_text(&self) -> String {
+        let mut text = String::new();
+        for diagnostic in &self.diagnostics {
+            text.push_str(&format!("{diagnostic}\n"));
+        }
+        text.push_str(&format!(
+            "{} errors, {} warnings",
+            self.with_severity(Severity::Error).count(),
+            self.with_severity(Severity::Warning).count()
+        ));
+        text
+    }
+
+    fn sarif(&self) -> Value {
+        let rules: Vec<Value> = <DiagnosticKind as clap::ValueEnum>::value_variants()
+            .iter()
+            .map(|kind| {
+                json!({
+                    "id": kind.id(),
+                    "shortDescription": { "text": kind.description() },
+                    "defaultConfiguration": { "level": kind.severity().to_string() },
+                })
+            })
+            .collect();
+        let results: Vec<Value> = self
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let mut location = json!({
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": diagnostic.file.to_string() },
+                    },
+                    "logicalLocations": [{ "fullyQualifiedName": diagnostic.path }],
+                });
+                if let Some(line) = diagnostic.line {
+                    location["physicalLocation"]["region"] = json!({ "startLine": line });
+                }
+                json!({
+                    "ruleId": diagnostic.kind.id(),
+                    "level": diagnostic.severity.to_string(),
+                    "message": { "text": diagnostic.message },
+                    "locations": [location],
+                })
+            })
+            .collect();
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules,
+                    },
+                },
+                "results": results,
+            }],
+        })
+    }
+}
+
+/// Appends an object key to a JSON path.
+pub(in crate::layers) fn join_key(path: &str, key: &str) -> String {
+    let is_identifier = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if is_identifier {
+        format!("{path}.{key}")
+    } else {
+        format!("{path}[{}]", Value::from(key))
+    }
+}
+
+/// Appends an array index to a JSON path.
+pub(in crate::layers) fn join_index(path: &str, index: usize) -> String {
+    format!("{path}[{index}]")
+}
+
+/// The lines at which the entries of a JSON5 document start, by their JSON path.
+#[derive(Debug, Default)]
+pub(in crate::layers) struct LineIndex(HashMap<String, usize>);
+
+impl LineIndex {
+    /// Indexes `text`. Malformed documents are indexed up to the first point they can't be read.
+    pub fn new(text: &str) -> Self {
+        let mut scanner = LineScanner {
+            chars: text.chars().peekable(),
+            line: 1,
+            lines: HashMap::new(),
+        };
+        scanner.value("$".to_owned());
+        Self(scanner.lines)
+    }
+
+    /// The line of the entry at `path`.
+    /// If the entry doesn't exist, the line of its closest existing parent is returned.
+    pub fn line(&self, mut path: &str) -> Option<usize> {
+        loop {
+            if let Some(line) = self.0.get(path) {
+                return Some(*line);
+            }
+            path = &path[..path.rfind(['.', '['])?];
+        }
+    }
+}
+
+/// A minimal JSON5 reader that only keeps track of where values start.
+struct LineScanner<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    lines: HashMap<String, usize>,
+}
+
+impl LineScanner<'_> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.next();
+                }
+                Some('/') => {
+                    let mut ahead = self.chars.clone();
+                    ahead.next();
+                    match ahead.next() {
+                        Some('/') => while !matches!(self.next(), None | Some('\n')) {},
+                        Some('*') => {
+                            self.next();
+                            self.next();
+                            let mut previous = ' ';
+                            while let Some(c) = self.next() {
+                                if previous == '*' && c == '/' {
+                                    break;
+                                }
+                                previous = c;
+                            }
+                        }
+                        _ => return,
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn value(&mut self, path: String) -> Option<()> {
+        self.skip_whitespace_and_comments();
+        self.lines.entry(path.clone()).or_insert(self.line);
+        match *self.chars.peek()? {
+            '{' => {
+                self.next();
+                loop {
+                    self.skip_whitespace_and_comments();
+                    match *self.chars.peek()? {
+                        '}' => {
+                            self.next();
+                            return Some(());
+                        }
+                        ',' => {
+                            self.next();
+                        }
+                        _ => {
+                            let key = self.key()?;
+                            self.skip_whitespace_and_comments();
+                            if self.next()? != ':' {
+                                return None;
+                            }
+                            self.value(join_key(&path, &key))?;
+                        }
+                    }
+                }
+            }
+            '[' => {
+                self.next();
+                let mut index = 0;
+                loop {
+                    self.skip_whitespace_and_comments();
+                    match *self.chars.peek()? {
+                        ']' => {
+                            self.next();
+                            return Some(());
+                        }
+                        ',' => {
+                            self.next();
+                        }
+                        _ => {
+                            self.value(join_index(&path, index))?;
+                            index += 1;
+                        }
+                    }
+                }
+            }
+            '"' | '\'' => self.string().map(|_| ()),
+            _ => {
+                self.next();
+                while matches!(self.chars.peek(), Some(c) if !c.is_whitespace() && !matches!(c, ',' | ']' | '}' | '/'))
+                {
+                    self.next();
+                }
+                Some(())
+            }
+        }
+    }
+
+    fn key(&mut self) -> Option<String> {
+        if matches!(self.chars.peek()?, '"' | '\'') {
+            return self.string();
+        }
+        let mut key = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() || matches!(c, ':' | '/') {
+                break;
+            }
+            key.push(c);
+            self.next();
+        }
+        Some(key)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let quote = self.next()?;
+        let mut string = String::new();
+        loop {
+            match self.next()? {
+                '\\' => {
+                    let c = self.next()?;
+                    if c != '\n' {
+                        string.push(c);
+                    }
+                }
+                c if c == quote => return Some(string),
+                c => string.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum;
+
+    use super::*;
+
+    fn report() -> LayerReport {
+        let location = LayerLocation::parse("/layers/root").unwrap();
+        let origin = |line, path: &str| Origin {
+            location: location.clone(),
+            line,
+            path: path.to_owned(),
+        };
+        LayerReport {
+            diagnostics: vec![
+                origin(Some(3), "$.layers[0]")
+                    .diagnostic(DiagnosticKind::InvalidEntry, "Unknown layer: a"),
+                origin(None, "$.groups[1]")
+                    .diagnostic(DiagnosticKind::UnusedGroup, "Group \"b\" is unused."),
+            ],
+        }
+    }
+
+    #[test]
+    fn lines_skip_comments() {
+        let lines = LineIndex::new(
+            "// {\n{\n  /* layers: [\n  */ layers: [\n    // 'x',\n    'a', /* 'y', */ 'b',\n  ],\n}",
+        );
+        assert_eq!(lines.line("$"), Some(2));
+        assert_eq!(lines.line("$.layers"), Some(4));
+        assert_eq!(lines.line("$.layers[0]"), Some(6));
+        assert_eq!(lines.line("$.layers[1]"), Some(6));
+        assert_eq!(lines.line("$.layers[2]"), Some(4));
+    }
+
+    #[test]
+    fn lines_handle_quoted_keys_and_escapes() {
+        let lines =
+            LineIndex::new("{\n  'a b': 1,\n  \"c\\\"d\": 'e\\\n    f',\n  g: \"}\",\n  h: 2,\n}");
+        assert_eq!(lines.line(&join_key("$", "a b")), Some(2));
+        assert_eq!(lines.line(&join_key("$", "c\"d")), Some(3));
+        assert_eq!(lines.line("$.g"), Some(5));
+        assert_eq!(lines.line("$.h"), Some(6));
+    }
+
+    #[test]
+    fn lines_fall_back_to_the_closest_parent() {
+        let lines = LineIndex::new(
+            "{\n  groups: [\n    {\n      id: 'a',\n      children: [\n        'b',\n      ],\n    },\n  ],\n}",
+        );
+        assert_eq!(lines.line("$.groups[0].children[0]"), Some(6));
+        assert_eq!(lines.line("$.groups[0].children[1]"), Some(5));
+        assert_eq!(lines.line("$.groups[0].access.env"), Some(3));
+        assert_eq!(lines.line("$.layers"), Some(1));
+        assert_eq!(LineIndex::new("{ a: [").line("$.b"), Some(1));
+    }
+
+    #[test]
+    fn keys_are_quoted_unless_identifiers() {
+        assert_eq!(join_key("$", "tiff_displays"), "$.tiff_displays");
+        assert_eq!(join_key("$", "a b"), "$[\"a b\"]");
+        assert_eq!(join_key("$", ""), "$[\"\"]");
+        assert_eq!(join_index("$.layers", 2), "$.layers[2]");
+    }
+
+    #[test]
+    fn renders_text() {
+        assert_eq!(
+            report().render(ReportFormat::Text),
+            "error[invalid-entry] /layers/root.json5:3 at $.layers[0]: Unknown layer: a\n\
+             warning[unused-group] /layers/root.json5 at $.groups[1]: Group \"b\" is unused.\n\
+             1 errors, 1 warnings"
+        );
+    }
+
+    #[test]
+    fn renders_json() {
+        let json: Value = serde_json::from_str(&report().render(ReportFormat::Json)).unwrap();
+        assert_eq!(json["errors"], 1);
+        assert_eq!(json["warnings"], 1);
+        assert_eq!(
+            json["diagnostics"][0],
+            json!({
+                "severity": "error",
+                "kind": "invalid-entry",
+                "file": "/layers/root.json5",
+                "line": 3,
+                "path": "$.layers[0]",
+                "message": "Unknown layer: a",
+            })
+        );
+        assert_eq!(json["diagnostics"][1].get("line"), None);
+    }
+
+    #[test]
+    fn renders_sarif() {
+        let sarif: Value = serde_json::from_str(&report().render(ReportFormat::Sarif)).unwrap();
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), DiagnosticKind::value_variants().len());
+        assert_eq!(rules[0]["id"], "invalid-file");
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["ruleId"], "invalid-entry");
+        assert_eq!(results[0]["level"], "error");
+        assert_eq!(results[0]["message"]["text"], "Unknown layer: a");
+        let location = &results[0]["locations"][0];
+        assert_eq!(
+            location["physicalLocation"],
+            json!({
+                "artifactLocation": { "uri": "/layers/root.json5" },
+                "region": { "startLine": 3 },
+            })
+        );
+        assert_eq!(
+            location["logicalLocations"][0]["fullyQualifiedName"],
+            "$.layers[0]"
+        );
+        assert_eq!(results[1]["level"], "warning");
+        assert_eq!(
+            results[1]["locations"][0]["physicalLocation"].get("region"),
+            None
+        );
+    }
+}
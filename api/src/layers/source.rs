@@ -1,6 +1,7 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum LayerSource {
     #[serde(rename_all(serialize = "camelCase"))]
@@ -28,7 +29,7 @@ pub enum LayerSource {
     Ogc(OgcLayerSource),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all(serialize = "camelCase"))]
 pub struct OgcLayerSource {
     pub ogc_source: OgcSource,
@@ -37,7 +38,7 @@ pub struct OgcLayerSource {
     pub display_source: Option<Box<LayerSource>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum OgcSource {
     #[serde(rename = "gst", rename_all(serialize = "camelCase"))]
@@ -1,6 +1,7 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct LayerStyle {
     /// Name of the property used for classification
@@ -10,7 +11,7 @@ pub struct LayerStyle {
 }
 
 /// Tagged union for style values, discriminated by `geomType`.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "geomType", rename_all = "camelCase")]
 pub enum LayerStyleValues {
     Point(PointStyleValues),
@@ -18,35 +19,35 @@ pub enum LayerStyleValues {
     Polygon(PolygonStyleValues),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PointStyleValues {
     pub value: StyleValue,
     pub vector_options: PointVectorOptions,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct LineStyleValues {
     pub value: StyleValue,
     pub vector_options: LineVectorOptions,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PolygonStyleValues {
     pub value: StyleValue,
     pub vector_options: PolygonVectorOptions,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum StyleValue {
     String(String),
     Number(f64),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct BasePointVectorOptions {
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -63,7 +64,7 @@ pub struct BasePointVectorOptions {
 }
 
 /// Point vector options for shape types (circle, triangle, square)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ShapePointVectorOptions {
     #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
@@ -74,7 +75,7 @@ pub struct ShapePointVectorOptions {
 }
 
 /// Point vector options for icon type (requires src)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct IconPointVectorOptions {
     #[serde(rename = "type")]
@@ -84,14 +85,14 @@ pub struct IconPointVectorOptions {
 }
 
 /// Union of shape and icon point vector options
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum PointVectorOptions {
     Icon(IconPointVectorOptions),
     Shape(ShapePointVectorOptions),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum ShapeMarkerType {
     Circle,
@@ -99,20 +100,20 @@ pub enum ShapeMarkerType {
     Square,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum IconMarkerType {
     Icon,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct LineVectorOptions {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub stroke: Option<StrokeStyle>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PolygonVectorOptions {
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -122,14 +123,14 @@ pub struct PolygonVectorOptions {
     pub stroke: Option<StrokeStyle>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FillStyle {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub color: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct StrokeStyle {
     #[serde(default, skip_serializing_if = "Option::is_none")]
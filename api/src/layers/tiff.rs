@@ -1,6 +1,7 @@
 use crate::LayerSource;
 use crate::layers::config::{Parse, ParseContext};
 use anyhow::anyhow;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize, Serializer};
 use std::collections::{BTreeMap, HashMap};
 use std::fs;
@@ -9,7 +10,7 @@ use std::path::Path;
 use std::sync::LazyLock;
 use strum::{EnumIter, IntoEnumIterator};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all(serialize = "camelCase"))]
 pub struct TiffLayer {
     /// The layer's source, defining where the layer is loaded from.
@@ -27,7 +28,7 @@ pub struct TiffLayer {
     pub bands: Vec<TiffLayerBand>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all(serialize = "camelCase"))]
 pub struct TiffLayerBand {
     /// The band's index within the TIFF.
@@ -50,14 +51,14 @@ pub struct TiffLayerBand {
     pub display: Option<TiffLayerBandDisplay>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum TiffLayerBandDisplay {
     Reference(String),
     Definition(TiffLayerBandDisplayDefinition),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all(serialize = "camelCase"))]
 pub struct TiffLayerBandDisplayDefinition {
     /// The lower and upper bounds of displayed values.
@@ -101,7 +102,7 @@ pub struct TiffLayerBandDisplayDefinition {
     pub use_count: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum TiffLayerBandSteps {
     /// Evenly separated steps, labeled with the array's elements.
@@ -111,7 +112,7 @@ pub enum TiffLayerBandSteps {
     Values(Vec<TiffLayerBandStepValue>),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum TiffLayerBandStepValue {
     /// A simple step value. Will be labelled with the value itself.
@@ -122,13 +123,13 @@ pub enum TiffLayerBandStepValue {
     Labelled { value: i32, label: String },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum TiffLayerUnit {
     Meters,
     MetersAboveSeaLevel,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, EnumIter)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, EnumIter, JsonSchema)]
 pub enum TiffColorMapName {
     #[serde(rename = "swissBEDROCK_BEM")]
     SwissBedrockBEM,
@@ -215,10 +216,11 @@ impl Parse for TiffLayerBand {
         self.display = match self.display {
             None => None,
             Some(TiffLayerBandDisplay::Reference(name)) => {
-                let definition =
-                    context.config.tiff_displays.get_mut(&name).ok_or_else(|| {
-                        anyhow!("[{}] Unknown tiff display: {name}", context.display)
-                    })?;
+                let definition = context
+                    .config
+                    .tiff_displays
+                    .get_mut(&name)
+                    .ok_or_else(|| anyhow!("Unknown tiff display: {name}"))?;
                 definition.use_count += 1;
                 Some(TiffLayerBandDisplay::Definition(definition.clone()))
             }
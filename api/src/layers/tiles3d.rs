@@ -1,9 +1,10 @@
 use crate::LayerSource;
 use crate::layers::config::{Parse, ParseContext};
 use anyhow::anyhow;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all(serialize = "camelCase"))]
 pub struct Tiles3dLayer {
     /// The layer's source, defining where the layer is loaded from.
@@ -18,7 +19,7 @@ pub struct Tiles3dLayer {
     pub order_of_properties: Tiles3dLayerOrderOfProperties,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum Tiles3dLayerOrderOfProperties {
     Reference(String),
@@ -47,14 +48,11 @@ impl Parse for Tiles3dLayer {
         self.order_of_properties = match self.order_of_properties {
             order @ Tiles3dLayerOrderOfProperties::Definition(_) => order,
             Tiles3dLayerOrderOfProperties::Reference(name) => {
-                let definition =
-                    context
-                        .config
-                        .order_of_properties
-                        .get(&name)
-                        .ok_or_else(|| {
-                            anyhow!("[{}] Unknown order of properties: {name}", context.display)
-                        })?;
+                let definition = context
+                    .config
+                    .order_of_properties
+                    .get(&name)
+                    .ok_or_else(|| anyhow!("Unknown order of properties: {name}"))?;
                 Tiles3dLayerOrderOfProperties::Definition(definition.clone())
             }
         };
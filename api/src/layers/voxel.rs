@@ -1,9 +1,11 @@
 use crate::LayerSource;
 use crate::layers::config::{Parse, ParseContext};
 use anyhow::anyhow;
+use schemars::{JsonSchema, Schema, SchemaGenerator};
 use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Cow;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all(serialize = "camelCase"))]
 pub struct VoxelLayer {
     pub source: LayerSource,
@@ -19,7 +21,7 @@ pub struct VoxelLayer {
     pub mappings: Vec<VoxelLayerMapping>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all(serialize = "camelCase"))]
 pub struct VoxelLayerValues {
     /// The value that represents an absent datapoint.
@@ -34,7 +36,7 @@ pub struct VoxelLayerValues {
     pub undefined: i32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum VoxelLayerMapping {
     Reference(String),
@@ -61,14 +63,14 @@ impl Parse for VoxelLayer {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum VoxelMappingDefinition {
     Range(VoxelRangeMapping),
     Category(VoxelItemMapping),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all(serialize = "camelCase"))]
 pub struct VoxelRangeMapping {
     /// The key of the property that contains the data points.
@@ -92,7 +94,7 @@ pub struct VoxelRangeMapping {
     pub use_count: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all(serialize = "camelCase"))]
 pub struct VoxelItemMapping {
     /// The key of the property that contains the data points.
@@ -124,19 +126,28 @@ pub struct VoxelItemMappingItem {
     pub color: String,
 }
 
+/// The way in which a [VoxelItemMappingItem] is written within the configuration,
+/// as tuple `(i32, { label: String, color: String })`.
+type VoxelItemMappingItemDefinition = (i32, VoxelItemMappingItemDetail);
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct VoxelItemMappingItemDetail {
+    /// The translation key providing the display name for the item.
+    pub label: String,
+
+    /// The color in which this value is displayed.
+    pub color: String,
+}
+
 /// Custom Deserialize implementation for [VoxelItemMappingItem] that allows
-/// the configuration to be written as tuple `(i32, { label: String, color: String })`.
+/// the configuration to be written as [VoxelItemMappingItemDefinition].
 impl<'de> Deserialize<'de> for VoxelItemMappingItem {
     fn deserialize<D>(d: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        #[derive(Deserialize)]
-        struct Item {
-            pub label: String,
-            pub color: String,
-        }
-        let (value, item) = <(i32, Item)>::deserialize(d)?;
+        let (value, item) = VoxelItemMappingItemDefinition::deserialize(d)?;
         Ok(Self {
             label: item.label,
             value,
@@ -144,3 +155,13 @@ impl<'de> Deserialize<'de> for VoxelItemMappingItem {
         })
     }
 }
+
+impl JsonSchema for VoxelItemMappingItem {
+    fn schema_name() -> Cow<'static, str> {
+        "VoxelItemMappingItem".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        VoxelItemMappingItemDefinition::json_schema(generator)
+    }
+}
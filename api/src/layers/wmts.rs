@@ -1,7 +1,8 @@
 use crate::OgcSource;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum WmtsLayerSource {
     #[serde(rename = "WMS")]
     Wms,
@@ -10,7 +11,7 @@ pub enum WmtsLayerSource {
     Wmts,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all(serialize = "camelCase"))]
 pub struct WmtsLayer {
     /// The source of the layer.
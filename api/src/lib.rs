@@ -6,24 +6,30 @@ use axum::{
 };
 use clap::Parser;
 use hyper::header::{
-    ACCEPT, AUTHORIZATION, CONTENT_SECURITY_POLICY, CONTENT_TYPE, HeaderName, REFERRER_POLICY,
-    STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+    ACCEPT, AUTHORIZATION, CONTENT_SECURITY_POLICY, CONTENT_TYPE, ETAG, HeaderName, IF_MATCH,
+    REFERRER_POLICY, STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
 };
 use sqlx::PgPool;
+use std::sync::Arc;
 use tower::ServiceBuilder;
 use tower_http::{cors::CorsLayer, set_header::SetResponseHeaderLayer, trace::TraceLayer};
 
 pub use config::{ClientConfig, Config};
 pub use error::Error;
+pub use projects::{AssetGc, AssetGcReport};
 
+mod admin;
+mod audit;
 mod auth;
 mod config;
 mod data;
 mod database;
 mod error;
 mod handlers;
+mod projects;
 mod s3;
 mod utils;
+mod validation;
 
 mod layers;
 pub use layers::*;
@@ -41,9 +47,22 @@ const CORS_ORIGINS: &[&str] = &[
     "https://viewer.swissgeol.ch",
 ];
 
-pub async fn app(pool: PgPool) -> Router {
-    let aws_config = s3::S3::parse();
-    let aws_client = aws_config.create_client().await;
+/// Creates the S3 client from the environment.
+/// The command line is ignored, as it holds the arguments of [Config].
+pub async fn create_s3_client() -> aws_sdk_s3::Client {
+    s3::S3::parse_from(std::env::args().take(1))
+        .create_client()
+        .await
+}
+
+pub async fn app(pool: PgPool, aws_client: aws_sdk_s3::Client) -> Router {
+    let asset_limits = projects::assets::AssetLimits::parse();
+    let invitation_config = projects::InvitationConfig::parse();
+    let admin_config = admin::AdminConfig::parse();
+    let trash = projects::ProjectTrash::parse();
+    trash.clone().spawn(pool.clone(), aws_client.clone());
+    let collaboration = projects::Collaboration::default();
+    collaboration.clone().spawn(pool.clone());
 
     let permissions_policy_header_name = HeaderName::from_static("permissions-policy");
     let security_headers = ServiceBuilder::new()
@@ -75,7 +94,10 @@ pub async fn app(pool: PgPool) -> Router {
     Router::new()
         .route("/api/client-config", get(handlers::get_client_config))
         .route("/api/layers", get(handlers::get_layer_config))
+        .route("/api/layers/schema", get(handlers::get_layer_schema))
         .route("/api/health_check", get(handlers::health_check))
+        .nest("/api/projects", projects::router())
+        .nest("/api/admin", admin::router())
         .layer(
             ServiceBuilder::new()
                 .layer(TraceLayer::new_for_http())
@@ -89,10 +111,16 @@ pub async fn app(pool: PgPool) -> Router {
                                 .map(|s| s.parse().expect("parse origin"))
                                 .collect::<Vec<HeaderValue>>(),
                         )
-                        .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE]),
+                        .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE, IF_MATCH])
+                        .expose_headers([ETAG, projects::search::NEXT_CURSOR]),
                 )
                 .layer(Extension(pool))
                 .layer(Extension(aws_client))
+                .layer(Extension(Arc::new(asset_limits)))
+                .layer(Extension(Arc::new(invitation_config)))
+                .layer(Extension(collaboration))
+                .layer(Extension(Arc::new(admin_config)))
+                .layer(Extension(Arc::new(trash)))
                 .layer(DefaultBodyLimit::max(2 * 1024 * 1024)), // 2 MB limit (default value). PROJECT_ASSET_MAX_SIZE should be updated on frontend after this value update
         )
         .layer(security_headers)
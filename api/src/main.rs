@@ -1,9 +1,10 @@
-use api::LayerConfig;
+use api::{LayerConfig, LayerLoader, LayerLocation, LiveLayerConfig};
 use axum::Extension;
 use clap::Parser;
 use std::net::SocketAddr;
 use std::process::exit;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -20,15 +21,37 @@ async fn main() -> anyhow::Result<()> {
     // Panic if we can't parse configuration
     let config = api::Config::parse();
 
+    if config.should_only_print_layers_schema {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&LayerConfig::json_schema())?
+        );
+        return Ok(());
+    }
+
     // Initialize JSON Web Key Set (JWKS)
     config.auth.initialize().await?;
 
+    let s3_client = api::create_s3_client().await;
+
     // Read the layer config file
-    let layers_file_path = std::path::Path::new(&config.layers_file);
-    let layers = LayerConfig::parse(layers_file_path).unwrap_or_else(|err| {
-        tracing::error!("{err}");
-        exit(1)
-    });
+    let layers_location = LayerLocation::parse(&config.layers_file)?;
+    let layers_loader = LayerLoader::new(s3_client.clone());
+
+    if config.should_only_validate {
+        let (_, report) = LayerConfig::validate(&layers_location, &layers_loader).await;
+        if !config.layers_validation.conclude(report) {
+            exit(1)
+        }
+        return Ok(());
+    }
+
+    let layers = LayerConfig::load(&layers_location, &layers_loader)
+        .await
+        .unwrap_or_else(|err| {
+            tracing::error!("{err}");
+            exit(1)
+        });
 
     tracing::info!(
         "Found {layers} layers in {groups} root groups.",
@@ -36,20 +59,34 @@ async fn main() -> anyhow::Result<()> {
         layers = layers.layers.len()
     );
 
-    if config.should_only_validate {
-        tracing::info!("Validation successful, exiting.");
+    // Set up a database connection pool & run any pending migrations
+    let pool = config.database.setup().await;
+
+    if config.should_only_gc_assets {
+        let report = config.asset_gc.run(&pool, &s3_client).await?;
+        report.log();
+        println!("{}", serde_json::to_string_pretty(&report)?);
         return Ok(());
     }
 
     let client_config = api::ClientConfig::parse();
 
-    // Set up a database connection pool & run any pending migrations
-    let pool = config.database.setup().await;
+    // Reload the layer config whenever its files change
+    let layers = LiveLayerConfig::new(layers_location, layers_loader, layers).await;
+    layers
+        .clone()
+        .watch(Duration::from_secs(config.layers_reload_interval));
+
+    // Collect unused assets in the background
+    config
+        .asset_gc
+        .clone()
+        .spawn(pool.clone(), s3_client.clone());
 
     // Build our application
-    let app = api::app(pool).await;
+    let app = api::app(pool, s3_client).await;
     let app = app
-        .layer(Extension(Arc::new(layers)))
+        .layer(Extension(layers))
         .layer(Extension(Arc::new(client_config)));
 
     // run our app with hyper
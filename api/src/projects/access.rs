@@ -0,0 +1,144 @@
+use std::marker::PhantomData;
+
+use anyhow::Context;
+use axum::extract::{Extension, FromRequestParts, Path};
+use axum::{async_trait, http::request::Parts};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::Error;
+use crate::auth::Claims;
+use crate::projects::Project;
+
+/// The role a user has within a project.
+///
+/// Roles are ordered by the permissions they grant,
+/// i.e. each role includes all permissions of the roles below it.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, sqlx::Type,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "project_role", rename_all = "lowercase")]
+pub enum ProjectRole {
+    /// May read the project.
+    Viewer,
+
+    /// May modify the project's content, i.e. its views, assets and geometries.
+    Editor,
+
+    /// May do anything with the project, including managing its members and deleting it.
+    Owner,
+}
+
+impl Project {
+    /// Determines the role that the user with the given email has within this project.
+    /// Returns `None` if the user is not a member of the project.
+    ///
+    /// If a user appears multiple times within the project, their highest role is returned.
+    pub fn role_of(&self, email: &str) -> Option<ProjectRole> {
+        let email = email.to_lowercase();
+        if self.owner.email.to_lowercase() == email {
+            return Some(ProjectRole::Owner);
+        }
+        if self
+            .editors
+            .iter()
+            .any(|it| it.email.to_lowercase() == email)
+        {
+            return Some(ProjectRole::Editor);
+        }
+        if self
+            .viewers
+            .iter()
+            .any(|it| it.email.to_lowercase() == email)
+        {
+            return Some(ProjectRole::Viewer);
+        }
+        None
+    }
+}
+
+/// A marker type defining the minimal [ProjectRole] required by [ProjectAccess].
+pub trait RequiredRole: Send + Sync {
+    const ROLE: ProjectRole;
+}
+
+/// Requires the user to be at least a [ProjectRole::Viewer].
+pub struct Viewer;
+
+/// Requires the user to be at least a [ProjectRole::Editor].
+pub struct Editor;
+
+/// Requires the user to be the [ProjectRole::Owner].
+pub struct Owner;
+
+impl RequiredRole for Viewer {
+    const ROLE: ProjectRole = ProjectRole::Viewer;
+}
+
+impl RequiredRole for Editor {
+    const ROLE: ProjectRole = ProjectRole::Editor;
+}
+
+impl RequiredRole for Owner {
+    const ROLE: ProjectRole = ProjectRole::Owner;
+}
+
+/// Extracts the project identified by the `id` path parameter,
+/// and ensures that the current user has at least the role `R` within it.
+///
+/// Users that are not a member of the project are answered with `404 Not Found`,
+/// so that the existence of a project is not revealed to them.
+/// Members lacking the required role are answered with `403 Forbidden`.
+pub struct ProjectAccess<R: RequiredRole> {
+    /// The project, as currently stored.
+    pub project: Project,
+
+    /// The role of the current user within the project.
+    pub role: ProjectRole,
+
+    /// The claims of the current user.
+    pub claims: Claims,
+
+    _role: PhantomData<R>,
+}
+
+#[derive(Deserialize)]
+struct ProjectPath {
+    id: Uuid,
+}
+
+#[async_trait]
+impl<S, R> FromRequestParts<S> for ProjectAccess<R>
+where
+    S: Send + Sync,
+    R: RequiredRole,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request_parts(parts, state).await?;
+        let Path(ProjectPath { id }) = Path::<ProjectPath>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| Error::NotFound)?;
+        let Extension(pool) = Extension::<PgPool>::from_request_parts(parts, state)
+            .await
+            .context("Database pool is not available")?;
+
+        let mut conn = pool.acquire().await?;
+        let project = Project::fetch(&mut conn, id)
+            .await?
+            .ok_or(Error::NotFound)?;
+        let role = project.role_of(&claims.email).ok_or(Error::NotFound)?;
+        if role < R::ROLE {
+            return Err(Error::Forbidden);
+        }
+        Ok(Self {
+            project,
+            role,
+            claims,
+            _role: PhantomData,
+        })
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::{HashMap, HashSet};
+
+use aws_sdk_s3::Client;
+use axum::extract::{Extension, Json, Path, Query};
+use axum::http::StatusCode;
+use axum::http::header::ETAG;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::admin::Admin;
+use crate::audit::{AuditAction, AuditEntry, AuditTarget};
+use crate::projects::assets::projects_bucket;
+use crate::projects::gc::list_objects;
+use crate::projects::search::ProjectQuery;
+use crate::projects::trash;
+use crate::projects::{Member, Project, ProjectRevision, ProjectRole, etag};
+use crate::{Error, Result};
+
+#[derive(Deserialize)]
+pub struct MemberFilter {
+    /// The email of a user that needs to be a member of the projects.
+    member: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ReassignOwner {
+    pub email: String,
+    /// Defaults to the user's current name, if they are already a member.
+    pub name: Option<String>,
+    /// Defaults to the user's current surname, if they are already a member.
+    pub surname: Option<String>,
+}
+
+/// A deleted project, in the state of its latest revision.
+/// The project may still be in the trash, or may have been purged from it.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedProject {
+    #[serde(flatten)]
+    pub project: Project,
+
+    /// When the project was deleted, and by whom.
+    /// Unknown for projects deleted before the audit log was introduced.
+    pub deleted: Option<DateTime<Utc>>,
+    pub deleted_by: Option<String>,
+}
+
+/// The storage used by project assets.
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsage {
+    /// All saved assets.
+    pub saved: StorageTotal,
+
+    /// Uploaded assets that have not been saved to a project yet.
+    pub uploads: StorageTotal,
+
+    /// Saved assets that are not referenced by any existing project or its revisions.
+    pub unreferenced: StorageTotal,
+
+    /// The storage used by each project that has assets, largest first.
+    /// Assets referenced by several projects are counted for each of them.
+    pub projects: Vec<ProjectStorage>,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageTotal {
+    /// The combined size of the assets, in bytes.
+    pub size: u64,
+    pub count: usize,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStorage {
+    pub id: Uuid,
+    pub title: String,
+
+    /// The combined size of the project's assets, including the ones of past revisions, in bytes.
+    pub size: u64,
+    pub asset_count: usize,
+}
+
+impl StorageTotal {
+    fn add(&mut self, size: u64) {
+        self.size += size;
+        self.count += 1;
+    }
+}
+
+/// Searches all projects, regardless of their members.
+#[axum_macros::debug_handler]
+pub async fn list_projects(
+    Extension(pool): Extension<PgPool>,
+    _admin: Admin,
+    Query(MemberFilter { member }): Query<MemberFilter>,
+    Query(query): Query<ProjectQuery>,
+) -> Result<impl IntoResponse> {
+    let mut conn = pool.acquire().await?;
+    let page = Project::search(&mut conn, member.as_deref(), &query).await?;
+    let projects = Project::fetch_all(&mut conn, &page.ids).await?;
+    Ok((page.headers()?, Json(projects)))
+}
+
+/// Makes a user the owner of a project, whether they are already a member or not.
+/// The previous owner stays in the project as an editor.
+#[axum_macros::debug_handler]
+pub async fn reassign_owner(
+    Extension(pool): Extension<PgPool>,
+    admin: Admin,
+    Path(id): Path<Uuid>,
+    Json(request): Json<ReassignOwner>,
+) -> Result<impl IntoResponse> {
+    let mut tx = pool.begin().await?;
+    sqlx::query!("SELECT id FROM projects WHERE id = $1 FOR UPDATE", id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let mut project = Project::fetch(&mut tx, id).await?.ok_or(Error::NotFound)?;
+    if request.email.to_lowercase() == project.owner.email.to_lowercase() {
+        return Ok((StatusCode::NO_CONTENT, [(ETAG, etag(project.revision))]));
+    }
+
+    let before = project.clone();
+    let current = project.member(&request.email).map(|it| it.member);
+    let owner = Member {
+        email: request.email.to_lowercase(),
+        name: request
+            .name
+            .or_else(|| current.as_ref().map(|it| it.name.clone()))
+            .unwrap_or_default(),
+        surname: request
+            .surname
+            .or_else(|| current.as_ref().map(|it| it.surname.clone()))
+            .unwrap_or_default(),
+    };
+    project.set_role(owner.clone(), None);
+    let previous_owner = std::mem::replace(&mut project.owner, owner);
+    project.set_role(previous_owner, Some(ProjectRole::Editor));
+    project.modified = Some(Utc::now());
+    project
+        .update(&mut tx, project.revision, &admin.claims.email)
+        .await?;
+    AuditEntry::record(
+        &mut tx,
+        &admin.claims.email,
+        AuditAction::MembersChanged,
+        AuditTarget::Project(project.id),
+        project.member_changes(&before),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::NO_CONTENT, [(ETAG, etag(project.revision))]))
+}
+
+/// Lists the projects that have been deleted, most recently deleted first.
+/// This includes the ones still in the trash, as well as the ones that have been purged from it.
+#[axum_macros::debug_handler]
+pub async fn list_deleted_projects(
+    Extension(pool): Extension<PgPool>,
+    _admin: Admin,
+) -> Result<Json<Vec<DeletedProject>>> {
+    let mut conn = pool.acquire().await?;
+    let projects = sqlx::query!(
+        r#"
+        SELECT r.snapshot AS "project!: sqlx::types::Json<Project>",
+               a.created AS "deleted?", a.actor AS "deleted_by?"
+        FROM (
+            SELECT DISTINCT ON (project_id) project_id, snapshot
+            FROM project_revisions
+            WHERE project_id NOT IN (SELECT id FROM projects WHERE deleted IS NULL)
+            ORDER BY project_id, revision DESC
+        ) r
+        LEFT JOIN LATERAL (
+            SELECT created, actor
+            FROM audit_log
+            WHERE action = 'project_deleted' AND target_type = 'project'
+                AND target_id = r.project_id::text
+            ORDER BY id DESC
+            LIMIT 1
+        ) a ON true
+        ORDER BY a.created DESC NULLS LAST
+        "#
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|row| DeletedProject {
+        project: row.project.0,
+        deleted: row.deleted,
+        deleted_by: row.deleted_by,
+    })
+    .collect();
+    Ok(Json(projects))
+}
+
+/// Restores a deleted project.
+///
+/// Projects in the trash are taken out of it as they are.
+/// Projects purged from the trash are restored from their latest revision,
+/// which is saved as a new revision. Their assets have been purged along with them,
+/// so the restored project doesn't have any.
+#[axum_macros::debug_handler]
+pub async fn restore_project(
+    Extension(pool): Extension<PgPool>,
+    admin: Admin,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    let mut tx = pool.begin().await?;
+    if let Some(project) = trash::fetch_trashed_for_update(&mut tx, id).await? {
+        trash::restore(&mut tx, &project, &admin.claims.email).await?;
+        tx.commit().await?;
+        return Ok(([(ETAG, etag(project.revision))], Json(project)));
+    }
+    if Project::fetch(&mut tx, id).await?.is_some() {
+        return Err(Error::Api(
+            StatusCode::CONFLICT,
+            "Project has not been deleted.",
+        ));
+    }
+    let revision = sqlx::query_scalar!(
+        "SELECT max(revision) FROM project_revisions WHERE project_id = $1",
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+    let mut project = ProjectRevision::fetch_snapshot(&mut tx, id, revision)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    project.assets.clear();
+    project.revision = revision + 1;
+    project.modified = Some(Utc::now());
+    project.insert(&mut tx, &admin.claims.email).await?;
+    AuditEntry::record(
+        &mut tx,
+        &admin.claims.email,
+        AuditAction::ProjectRestored,
+        AuditTarget::Project(project.id),
+        project.audit_summary(),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(([(ETAG, etag(project.revision))], Json(project)))
+}
+
+/// Reports the storage used by project assets.
+#[axum_macros::debug_handler]
+pub async fn get_storage_usage(
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    _admin: Admin,
+) -> Result<Json<StorageUsage>> {
+    let bucket = projects_bucket()?;
+    let mut usage = StorageUsage::default();
+    for (_, size) in list_objects(&client, &bucket, "assets/temp/", i64::MAX).await? {
+        usage.uploads.add(size);
+    }
+    let saved: HashMap<String, u64> = list_objects(&client, &bucket, "assets/saved/", i64::MAX)
+        .await?
+        .into_iter()
+        .collect();
+
+    let references = sqlx::query!(
+        r#"
+        SELECT p.id, p.title, k.key AS "key!"
+        FROM projects p
+        JOIN (
+            SELECT project_id, key FROM project_assets
+            UNION
+            SELECT project_id, asset->>'key'
+            FROM project_revisions, jsonb_array_elements(snapshot->'assets') AS asset
+        ) k ON k.project_id = p.id
+        "#
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let mut referenced = HashSet::new();
+    let mut projects: HashMap<Uuid, ProjectStorage> = HashMap::new();
+    for row in references {
+        let key = format!("assets/saved/{}", row.key);
+        let Some(size) = saved.get(&key) else {
+            continue;
+        };
+        referenced.insert(key);
+        let project = projects.entry(row.id).or_insert_with(|| ProjectStorage {
+            id: row.id,
+            title: row.title,
+            size: 0,
+            asset_count: 0,
+        });
+        project.size += size;
+        project.asset_count += 1;
+    }
+
+    for (key, size) in &saved {
+        usage.saved.add(*size);
+        if !referenced.contains(key) {
+            usage.unreferenced.add(*size);
+        }
+    }
+    usage.projects = projects.into_values().collect();
+    usage
+        .projects
+        .sort_by(|a, b| b.size.cmp(&a.size).then(a.id.cmp(&b.id)));
+    Ok(Json(usage))
+}
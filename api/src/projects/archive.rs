@@ -0,0 +1,261 @@
+use std::io::{Cursor, Read, Write};
+use std::sync::Arc;
+
+use anyhow::Context;
+use aws_sdk_s3::Client;
+use aws_sdk_s3::primitives::ByteStream;
+use axum::body::{Body, to_bytes};
+use axum::extract::{Extension, Json};
+use axum::http::StatusCode;
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::response::IntoResponse;
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::audit::{AuditAction, AuditEntry, AuditTarget};
+use crate::auth::Claims;
+use crate::projects::assets::{AssetFormat, AssetLimits, generate_asset_name, projects_bucket};
+use crate::projects::conversion::convert_to_kml;
+use crate::projects::geojson::GeoJsonCrs;
+use crate::projects::{Asset, Member, Project, ProjectAccess, Viewer};
+use crate::validation::validate;
+use crate::{Error, Result};
+
+const PROJECT_FILE: &str = "project.json";
+const VIEWS_FILE: &str = "views.json";
+const GEOMETRIES_WGS84_FILE: &str = "geometries.wgs84.geojson";
+const GEOMETRIES_LV95_FILE: &str = "geometries.lv95.geojson";
+const ASSETS_DIRECTORY: &str = "assets/";
+
+/// The space granted to an archive's content other than its assets, in bytes.
+const ARCHIVE_OVERHEAD: u64 = 10 * 1024 * 1024;
+
+/// An asset along with its content.
+type AssetContent = (Asset, Vec<u8>);
+
+/// Exports a project as a ZIP archive, which can be used without the viewer.
+///
+/// The archive contains the project itself, its geometries as GeoJSON in both WGS84 and LV95,
+/// its views and all of its assets as KML.
+#[axum_macros::debug_handler]
+pub async fn export_project(
+    Extension(client): Extension<Client>,
+    access: ProjectAccess<Viewer>,
+) -> Result<impl IntoResponse> {
+    let project = access.project;
+    let bucket = projects_bucket()?;
+
+    let mut assets = Vec::new();
+    for asset in &project.assets {
+        let key = format!("assets/saved/{}", asset.key);
+        let Ok(object) = client.get_object().bucket(&bucket).key(&key).send().await else {
+            tracing::warn!("Asset \"{key}\" of project {} is missing.", project.id);
+            continue;
+        };
+        let content = object
+            .body
+            .collect()
+            .await
+            .context("Failed to read asset")?
+            .into_bytes();
+        assets.push((asset.key.clone(), content.to_vec()));
+    }
+
+    let file_name = archive_name(&project.title);
+    let archive = tokio::task::spawn_blocking(move || write_archive(&project, &assets))
+        .await
+        .context("Failed to export project")??;
+
+    Ok((
+        [
+            (CONTENT_TYPE, "application/zip".to_owned()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{file_name}.zip\""),
+            ),
+        ],
+        archive,
+    ))
+}
+
+/// Creates a new project from an archive created by [export_project].
+/// The current user becomes the project's owner, and its only member.
+#[axum_macros::debug_handler]
+pub async fn import_project(
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    Extension(limits): Extension<Arc<AssetLimits>>,
+    claims: Claims,
+    body: Body,
+) -> Result<(StatusCode, Json<Uuid>)> {
+    let limit = limits.project_assets_max_total_size + ARCHIVE_OVERHEAD;
+    let data = to_bytes(body, limit as usize).await.map_err(|_| {
+        Error::Api(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Archive exceeds the maximum size.",
+        )
+    })?;
+    let archive_limits = limits.clone();
+    let (imported, assets) =
+        tokio::task::spawn_blocking(move || read_archive(&data, &archive_limits))
+            .await
+            .context("Failed to import project")??;
+    validate(&imported.geometries, "geometries")?;
+
+    for (_, content) in &assets {
+        convert_to_kml(AssetFormat::Kml, content, limits.project_asset_max_size)?;
+    }
+
+    let bucket = projects_bucket()?;
+    let mut project = Project {
+        id: Uuid::new_v4(),
+        title: imported.title,
+        description: imported.description,
+        created: Utc::now(),
+        modified: None,
+        revision: 1,
+        image: imported.image,
+        color: imported.color,
+        template: imported.template,
+        views: imported.views,
+        assets: Vec::new(),
+        owner: Member {
+            email: claims.email.to_lowercase(),
+            name: claims.given_name.clone(),
+            surname: claims.family_name.clone(),
+        },
+        viewers: Vec::new(),
+        editors: Vec::new(),
+        geometries: imported.geometries,
+    };
+    for (asset, content) in assets {
+        let key = generate_asset_name(AssetFormat::Kml);
+        client
+            .put_object()
+            .bucket(&bucket)
+            .key(format!("assets/saved/{key}"))
+            .content_type(AssetFormat::Kml.content_type())
+            .body(ByteStream::from(content))
+            .send()
+            .await
+            .context("Failed to save imported asset")?;
+        project.assets.push(Asset { key, ..asset });
+    }
+
+    let mut tx = pool.begin().await?;
+    project.insert(&mut tx, &claims.email).await?;
+    AuditEntry::record(
+        &mut tx,
+        &claims.email,
+        AuditAction::ProjectCreated,
+        AuditTarget::Project(project.id),
+        project.audit_summary(),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(project.id)))
+}
+
+fn write_archive(project: &Project, assets: &[(String, Vec<u8>)]) -> anyhow::Result<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut write = |name: &str, content: &[u8]| -> anyhow::Result<()> {
+        zip.start_file(name, options)?;
+        zip.write_all(content)?;
+        Ok(())
+    };
+    write(PROJECT_FILE, &serde_json::to_vec_pretty(project)?)?;
+    write(VIEWS_FILE, &serde_json::to_vec_pretty(&project.views)?)?;
+    write(
+        GEOMETRIES_WGS84_FILE,
+        &serde_json::to_vec_pretty(&project.geometries_to_geojson(GeoJsonCrs::Wgs84))?,
+    )?;
+    write(
+        GEOMETRIES_LV95_FILE,
+        &serde_json::to_vec_pretty(&project.geometries_to_geojson(GeoJsonCrs::Lv95))?,
+    )?;
+    for (key, content) in assets {
+        write(&format!("{ASSETS_DIRECTORY}{key}"), content)?;
+    }
+
+    Ok(zip
+        .finish()
+        .context("Failed to write archive")?
+        .into_inner())
+}
+
+/// Reads the project and the content of its assets from an archive.
+/// Assets whose content is missing from the archive are dropped.
+///
+/// Each asset may be as large as a direct upload, and all of them together as the project quota.
+fn read_archive(data: &[u8], limits: &AssetLimits) -> Result<(Project, Vec<AssetContent>)> {
+    let invalid = || Error::Api(StatusCode::BAD_REQUEST, "Invalid project archive.");
+    let too_large = || {
+        Error::Api(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Archive content exceeds the maximum size.",
+        )
+    };
+    let mut zip = ZipArchive::new(Cursor::new(data)).map_err(|_| invalid())?;
+
+    // The sizes declared by the archive are not trusted,
+    // so entries are never decompressed beyond their limit.
+    let mut read = |name: &str, max_size: u64| -> Result<Option<Vec<u8>>> {
+        let Ok(file) = zip.by_name(name) else {
+            return Ok(None);
+        };
+        if file.size() > max_size {
+            return Err(too_large());
+        }
+        let mut content = Vec::new();
+        if file.take(max_size + 1).read_to_end(&mut content).is_err() {
+            return Ok(None);
+        }
+        if content.len() as u64 > max_size {
+            return Err(too_large());
+        }
+        Ok(Some(content))
+    };
+    let project: Project =
+        serde_json::from_slice(&read(PROJECT_FILE, ARCHIVE_OVERHEAD)?.ok_or_else(invalid)?)
+            .map_err(|_| invalid())?;
+
+    let mut remaining_size = limits.project_assets_max_total_size;
+    let mut assets = Vec::new();
+    for asset in &project.assets {
+        let max_size = remaining_size.min(limits.project_asset_direct_upload_max_size);
+        match read(&format!("{ASSETS_DIRECTORY}{}", asset.key), max_size)? {
+            Some(content) => {
+                remaining_size -= content.len() as u64;
+                assets.push((asset.clone(), content));
+            }
+            None => tracing::warn!("Imported asset \"{}\" is missing.", asset.key),
+        }
+    }
+    Ok((project, assets))
+}
+
+/// Derives a file name for the archive of a project from its title.
+fn archive_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|it| {
+            if it.is_ascii_alphanumeric() || it == '-' {
+                it
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = name.trim_matches('_');
+    if name.is_empty() {
+        "project".to_owned()
+    } else {
+        name.to_owned()
+    }
+}
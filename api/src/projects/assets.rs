@@ -0,0 +1,644 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use aws_sdk_s3::Client;
+use aws_sdk_s3::presigning::{PresignedRequest, PresigningConfig};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use axum::extract::multipart::{Field, MultipartError};
+use axum::extract::{Extension, Json, Multipart, Path};
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use percent_encoding::{NON_ALPHANUMERIC, percent_decode_str, utf8_percent_encode};
+use rand::{Rng, distributions::Alphanumeric};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::Claims;
+use crate::projects::conversion::convert_to_kml;
+use crate::projects::{Asset, ProjectAccess, Viewer};
+use crate::{Error, Result};
+
+/// The minimal size of each part of a multipart upload, except for the last one.
+/// This is a requirement of S3.
+const PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// Limits for the assets that can be attached to projects.
+#[derive(clap::Parser, Debug, Clone)]
+pub struct AssetLimits {
+    /// The maximum size of a single project asset uploaded through the API, in bytes.
+    #[clap(long, env, default_value = "2097152")]
+    pub project_asset_max_size: u64,
+
+    /// The maximum size of a single project asset uploaded directly to S3
+    /// through a pre-signed URL, in bytes.
+    #[clap(long, env, default_value = "52428800")]
+    pub project_asset_direct_upload_max_size: u64,
+
+    /// The maximum combined size of all assets of a single project, in bytes.
+    #[clap(long, env, default_value = "52428800")]
+    pub project_assets_max_total_size: u64,
+
+    /// The time for which pre-signed asset URLs are valid, in seconds.
+    #[clap(long, env, default_value = "300")]
+    pub project_asset_url_ttl: u64,
+}
+
+/// The S3 metadata entry in which the name of an uploaded file is kept until the asset is saved.
+const ORIGINAL_NAME_METADATA: &str = "original-name";
+
+/// The file formats that are accepted as project assets.
+///
+/// Assets are uploaded in any of these formats,
+/// and converted to KML once they are saved to a project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "asset_format", rename_all = "lowercase")]
+pub enum AssetFormat {
+    Kml,
+    Kmz,
+    GeoJson,
+    Gpx,
+    Csv,
+    /// A zipped ESRI Shapefile.
+    Shapefile,
+}
+
+impl AssetFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AssetFormat::Kml => "kml",
+            AssetFormat::Kmz => "kmz",
+            AssetFormat::GeoJson => "geojson",
+            AssetFormat::Gpx => "gpx",
+            AssetFormat::Csv => "csv",
+            AssetFormat::Shapefile => "zip",
+        }
+    }
+
+    /// The name of the format, as shown to users.
+    pub fn label(&self) -> &'static str {
+        match self {
+            AssetFormat::Kml => "KML",
+            AssetFormat::Kmz => "KMZ",
+            AssetFormat::GeoJson => "GeoJSON",
+            AssetFormat::Gpx => "GPX",
+            AssetFormat::Csv => "CSV",
+            AssetFormat::Shapefile => "Shapefile",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            AssetFormat::Kml => "application/vnd.google-earth.kml+xml",
+            AssetFormat::Kmz => "application/vnd.google-earth.kmz",
+            AssetFormat::GeoJson => "application/geo+json",
+            AssetFormat::Gpx => "application/gpx+xml",
+            AssetFormat::Csv => "text/csv",
+            AssetFormat::Shapefile => "application/zip",
+        }
+    }
+
+    /// Determines the format from the extension of a file name or asset key.
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let extension = std::path::Path::new(file_name).extension()?.to_str()?;
+        match extension.to_lowercase().as_str() {
+            "kml" => Some(AssetFormat::Kml),
+            "kmz" => Some(AssetFormat::Kmz),
+            "geojson" | "json" => Some(AssetFormat::GeoJson),
+            "gpx" => Some(AssetFormat::Gpx),
+            "csv" => Some(AssetFormat::Csv),
+            "zip" => Some(AssetFormat::Shapefile),
+            _ => None,
+        }
+    }
+
+    /// Detects the format of a file from its leading bytes.
+    ///
+    /// The content is authoritative, the file name is only used to accept CSV,
+    /// as there's no reliable way to tell it apart from arbitrary text,
+    /// and to tell zipped Shapefiles apart from KMZ files.
+    pub fn sniff(head: &[u8], file_name: &str) -> Option<Self> {
+        if head.starts_with(b"PK\x03\x04") {
+            return match Self::from_file_name(file_name) {
+                Some(AssetFormat::Shapefile) => Some(AssetFormat::Shapefile),
+                _ => Some(AssetFormat::Kmz),
+            };
+        }
+        if head.contains(&0) {
+            return None;
+        }
+        let text = String::from_utf8_lossy(head);
+        let text = text.trim_start_matches('\u{feff}').trim_start();
+        if text.starts_with('<') {
+            if text.contains("<kml") {
+                return Some(AssetFormat::Kml);
+            }
+            if text.contains("<gpx") {
+                return Some(AssetFormat::Gpx);
+            }
+            return None;
+        }
+        if text.starts_with('{') {
+            return text.contains("\"type\"").then_some(AssetFormat::GeoJson);
+        }
+        let header = text.lines().next().unwrap_or_default();
+        let is_csv = Self::from_file_name(file_name) == Some(AssetFormat::Csv)
+            && [',', ';', '\t'].iter().any(|it| header.contains(*it));
+        is_csv.then_some(AssetFormat::Csv)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadResponse {
+    pub key: String,
+    pub original_name: String,
+    pub format: AssetFormat,
+}
+
+/// A short-lived URL through which an asset can be accessed directly in the bucket.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresignedUrl {
+    pub url: String,
+    pub method: String,
+    /// The headers that have to be sent along with the request.
+    pub headers: HashMap<String, String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PresignedUrl {
+    fn new(request: PresignedRequest, expires_in: Duration) -> Self {
+        Self {
+            url: request.uri().to_owned(),
+            method: request.method().to_owned(),
+            headers: request
+                .headers()
+                .map(|(name, value)| (name.to_owned(), value.to_owned()))
+                .collect(),
+            expires_at: Utc::now() + expires_in,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadUrlRequest {
+    pub file_name: String,
+    /// The exact size of the file, in bytes.
+    pub size: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadUrlResponse {
+    pub key: String,
+    pub original_name: String,
+    pub format: AssetFormat,
+    pub upload: PresignedUrl,
+}
+
+#[derive(Deserialize)]
+pub struct AssetPath {
+    key: String,
+}
+
+/// Creates a URL through which an asset of a project can be downloaded.
+#[axum_macros::debug_handler]
+pub async fn get_asset_url(
+    Extension(client): Extension<Client>,
+    Extension(limits): Extension<Arc<AssetLimits>>,
+    access: ProjectAccess<Viewer>,
+    Path(AssetPath { key }): Path<AssetPath>,
+) -> Result<Json<PresignedUrl>> {
+    if !access.project.assets.iter().any(|it| it.key == key) {
+        return Err(Error::NotFound);
+    }
+    let expires_in = Duration::from_secs(limits.project_asset_url_ttl);
+    let request = client
+        .get_object()
+        .bucket(projects_bucket()?)
+        .key(format!("assets/saved/{key}"))
+        .presigned(PresigningConfig::expires_in(expires_in).context("Invalid asset URL TTL")?)
+        .await
+        .context("Failed to presign asset download")?;
+    Ok(Json(PresignedUrl::new(request, expires_in)))
+}
+
+/// Creates a URL through which a file can be uploaded directly into the temporary asset storage,
+/// bypassing the request size limit of the API.
+///
+/// The file's content is only validated once the asset is saved to a project.
+#[axum_macros::debug_handler]
+pub async fn create_upload_url(
+    Extension(client): Extension<Client>,
+    Extension(limits): Extension<Arc<AssetLimits>>,
+    _claims: Claims,
+    Json(request): Json<UploadUrlRequest>,
+) -> Result<Json<UploadUrlResponse>> {
+    let format = AssetFormat::from_file_name(&request.file_name).ok_or(Error::Api(
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        "Unsupported asset format. Supported formats are KML, KMZ, GeoJSON, GPX, CSV and zipped Shapefiles.",
+    ))?;
+    if request.size > limits.project_asset_direct_upload_max_size {
+        return Err(Error::Api(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Asset exceeds the maximum file size.",
+        ));
+    }
+
+    let name = generate_asset_name(format);
+    let expires_in = Duration::from_secs(limits.project_asset_url_ttl);
+    // The size is part of the signature, so S3 rejects files that don't match it.
+    let upload = client
+        .put_object()
+        .bucket(projects_bucket()?)
+        .key(format!("assets/temp/{name}"))
+        .content_type(format.content_type())
+        .content_length(request.size as i64)
+        .metadata(
+            ORIGINAL_NAME_METADATA,
+            utf8_percent_encode(&request.file_name, NON_ALPHANUMERIC).to_string(),
+        )
+        .presigned(PresigningConfig::expires_in(expires_in).context("Invalid asset URL TTL")?)
+        .await
+        .context("Failed to presign asset upload")?;
+    Ok(Json(UploadUrlResponse {
+        key: name,
+        original_name: request.file_name,
+        format,
+        upload: PresignedUrl::new(upload, expires_in),
+    }))
+}
+
+/// Uploads a file into the temporary asset storage,
+/// from where it's moved once the asset is attached to a project.
+///
+/// The file is streamed to S3 instead of being buffered as a whole.
+pub async fn upload_asset(
+    Extension(client): Extension<Client>,
+    Extension(limits): Extension<Arc<AssetLimits>>,
+    _claims: Claims,
+    mut multipart: Multipart,
+) -> Result<Json<UploadResponse>> {
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        if field.name() == Some("file") {
+            return Ok(Json(upload_field(&client, &limits, field).await?));
+        }
+    }
+    Err(Error::Api(
+        StatusCode::BAD_REQUEST,
+        "Missing field \"file\".",
+    ))
+}
+
+/// Streams a multipart field into the temporary asset storage.
+async fn upload_field(
+    client: &Client,
+    limits: &AssetLimits,
+    mut field: Field<'_>,
+) -> Result<UploadResponse> {
+    let bucket = projects_bucket()?;
+    let file_name = field.file_name().unwrap_or_default().to_owned();
+
+    let mut size = 0;
+    let (mut buffer, mut is_complete) = read_part(&mut field, &mut size, limits).await?;
+    let format = AssetFormat::sniff(&buffer, &file_name).ok_or(Error::Api(
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        "Unsupported asset format. Supported formats are KML, KMZ, GeoJSON, GPX, CSV and zipped Shapefiles.",
+    ))?;
+    let name = generate_asset_name(format);
+    let key = format!("assets/temp/{name}");
+    let original_name = utf8_percent_encode(&file_name, NON_ALPHANUMERIC).to_string();
+    let response = UploadResponse {
+        key: name,
+        original_name: file_name,
+        format,
+    };
+
+    // Small files can be uploaded in one go.
+    // They are validated right away, larger ones only once they are saved.
+    if is_complete {
+        let max_size = limits.project_asset_max_size;
+        let buffer = tokio::task::spawn_blocking(move || {
+            convert_to_kml(format, &buffer, max_size).map(|_| buffer)
+        })
+        .await
+        .context("Failed to convert asset")??;
+        client
+            .put_object()
+            .bucket(&bucket)
+            .key(&key)
+            .content_type(format.content_type())
+            .metadata(ORIGINAL_NAME_METADATA, original_name)
+            .body(ByteStream::from(buffer))
+            .send()
+            .await
+            .context("Failed to upload asset")?;
+        return Ok(response);
+    }
+
+    let upload = client
+        .create_multipart_upload()
+        .bucket(&bucket)
+        .key(&key)
+        .content_type(format.content_type())
+        .metadata(ORIGINAL_NAME_METADATA, original_name)
+        .send()
+        .await
+        .context("Failed to start asset upload")?;
+    let upload_id = upload
+        .upload_id()
+        .context("Multipart upload is missing its id")?;
+
+    let mut parts = Vec::new();
+    let result: Result<()> = async {
+        loop {
+            let part_number = parts.len() as i32 + 1;
+            let part = client
+                .upload_part()
+                .bucket(&bucket)
+                .key(&key)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .body(ByteStream::from(buffer))
+                .send()
+                .await
+                .context("Failed to upload asset part")?;
+            parts.push(
+                CompletedPart::builder()
+                    .part_number(part_number)
+                    .set_e_tag(part.e_tag().map(str::to_owned))
+                    .build(),
+            );
+            if is_complete {
+                return Ok(());
+            }
+            (buffer, is_complete) = read_part(&mut field, &mut size, limits).await?;
+            if buffer.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+    .await;
+
+    if let Err(err) = result {
+        if let Err(abort_err) = client
+            .abort_multipart_upload()
+            .bucket(&bucket)
+            .key(&key)
+            .upload_id(upload_id)
+            .send()
+            .await
+        {
+            tracing::error!("Failed to abort upload of asset \"{key}\": {abort_err}");
+        }
+        return Err(err);
+    }
+
+    client
+        .complete_multipart_upload()
+        .bucket(&bucket)
+        .key(&key)
+        .upload_id(upload_id)
+        .multipart_upload(
+            CompletedMultipartUpload::builder()
+                .set_parts(Some(parts))
+                .build(),
+        )
+        .send()
+        .await
+        .context("Failed to complete asset upload")?;
+    Ok(response)
+}
+
+/// Reads chunks from `field` until at least [PART_SIZE] bytes are available, or the field ends.
+/// Returns the read bytes, and whether the field has been read completely.
+async fn read_part(
+    field: &mut Field<'_>,
+    size: &mut u64,
+    limits: &AssetLimits,
+) -> Result<(Vec<u8>, bool)> {
+    let mut buffer = Vec::with_capacity(PART_SIZE);
+    while buffer.len() < PART_SIZE {
+        let Some(chunk) = field.chunk().await.map_err(multipart_error)? else {
+            return Ok((buffer, true));
+        };
+        *size += chunk.len() as u64;
+        if *size > limits.project_asset_max_size {
+            return Err(Error::Api(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Asset exceeds the maximum file size.",
+            ));
+        }
+        buffer.extend_from_slice(&chunk);
+    }
+    Ok((buffer, false))
+}
+
+fn multipart_error(err: MultipartError) -> Error {
+    if err.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        return Error::Api(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Asset exceeds the maximum file size.",
+        );
+    }
+    tracing::debug!("Invalid multipart request: {err}");
+    Error::Api(StatusCode::BAD_REQUEST, "Invalid multipart request.")
+}
+
+/// Ensures that the combined size of `assets` does not exceed the project quota.
+/// Assets are looked up in both the temporary and the permanent storage.
+pub async fn ensure_within_quota(
+    client: &Client,
+    limits: &AssetLimits,
+    assets: &[Asset],
+) -> Result<()> {
+    let bucket = projects_bucket()?;
+    let mut total_size = 0;
+    for asset in assets {
+        for key in [
+            format!("assets/saved/{}", asset.key),
+            format!("assets/temp/{}", asset.key),
+        ] {
+            if let Ok(head) = client.head_object().bucket(&bucket).key(&key).send().await {
+                total_size += head.content_length().unwrap_or_default().max(0) as u64;
+                break;
+            }
+        }
+    }
+    if total_size > limits.project_assets_max_total_size {
+        return Err(Error::Api(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Project assets exceed the maximum total size.",
+        ));
+    }
+    Ok(())
+}
+
+/// Moves the given assets from the temporary into the permanent storage,
+/// converting them to KML on the way.
+/// Assets that are already stored permanently are left untouched.
+///
+/// The keys of moved assets are updated to the ones of the converted files,
+/// and their original file name and format are recorded.
+pub async fn save_assets(
+    client: &Client,
+    limits: &AssetLimits,
+    project_assets: &mut [Asset],
+) -> Result<()> {
+    let bucket = projects_bucket()?;
+    for asset in project_assets {
+        let temp_key = format!("assets/temp/{}", asset.key);
+
+        // Check if the file exists in the source directory
+        let Ok(upload) = client
+            .get_object()
+            .bucket(&bucket)
+            .key(&temp_key)
+            .send()
+            .await
+        else {
+            continue;
+        };
+
+        let format = AssetFormat::from_file_name(&asset.key).ok_or(Error::Api(
+            StatusCode::BAD_REQUEST,
+            "Asset has an unsupported format.",
+        ))?;
+        let original_name = upload
+            .metadata()
+            .and_then(|it| it.get(ORIGINAL_NAME_METADATA))
+            .map(|it| percent_decode_str(it).decode_utf8_lossy().into_owned());
+        let data = upload
+            .body
+            .collect()
+            .await
+            .context("Failed to read uploaded asset")?
+            .into_bytes();
+        // Uploads may have bypassed the API, so they can be as large as direct uploads.
+        let max_size = limits.project_asset_direct_upload_max_size;
+        let kml = tokio::task::spawn_blocking(move || convert_to_kml(format, &data, max_size))
+            .await
+            .context("Failed to convert asset")??;
+
+        let name = std::path::Path::new(&asset.key)
+            .with_extension(AssetFormat::Kml.extension())
+            .to_string_lossy()
+            .into_owned();
+        client
+            .put_object()
+            .bucket(&bucket)
+            .key(format!("assets/saved/{name}"))
+            .content_type(AssetFormat::Kml.content_type())
+            .body(ByteStream::from(kml))
+            .send()
+            .await
+            .context("Failed to save asset")?;
+
+        client
+            .delete_object()
+            .bucket(&bucket)
+            .key(&temp_key)
+            .send()
+            .await
+            .context("Failed to delete temporary asset")?;
+
+        asset.key = name;
+        asset.original_name = original_name;
+        asset.format = Some(format);
+    }
+    Ok(())
+}
+
+/// Copies saved assets to new keys, so that they can be referenced by another project.
+/// Assets that no longer exist in the bucket are skipped.
+pub async fn copy_assets(client: &Client, project_assets: &[Asset]) -> Result<Vec<Asset>> {
+    let mut assets: Vec<Asset> = Vec::new();
+    let bucket = projects_bucket()?;
+
+    for asset in project_assets {
+        let format = AssetFormat::from_file_name(&asset.key).unwrap_or(AssetFormat::Kml);
+        let generated_file_name: String = generate_asset_name(format);
+        let asset_key = format!("assets/saved/{}", asset.key);
+        let dest_key = format!("assets/saved/{}", generated_file_name);
+        // Check if the file exists in the source directory
+        let source_exists = client
+            .head_object()
+            .bucket(&bucket)
+            .key(&asset_key)
+            .send()
+            .await
+            .is_ok();
+
+        if source_exists {
+            client
+                .copy_object()
+                .copy_source(format!("{}/{}", &bucket, &asset_key))
+                .bucket(&bucket)
+                .key(&dest_key)
+                .send()
+                .await
+                .context("Failed to copy object")?;
+
+            assets.push(Asset {
+                name: asset.name.clone(),
+                key: generated_file_name,
+                clamp_to_ground: asset.clamp_to_ground,
+                original_name: asset.original_name.clone(),
+                format: asset.format,
+            });
+        }
+    }
+    Ok(assets)
+}
+
+/// Deletes the given assets from the permanent storage.
+pub async fn delete_assets(client: &Client, project_assets: &[Asset]) -> Result<()> {
+    let bucket = projects_bucket()?;
+    for asset in project_assets {
+        let permanent_key = format!("assets/saved/{}", asset.key);
+
+        // Check if the file exists in the destination directory
+        let destination_exists = client
+            .head_object()
+            .bucket(&bucket)
+            .key(&permanent_key)
+            .send()
+            .await
+            .is_ok();
+
+        if destination_exists {
+            client
+                .delete_object()
+                .bucket(&bucket)
+                .key(&permanent_key)
+                .send()
+                .await
+                .context("Failed to delete asset")?;
+        }
+    }
+    Ok(())
+}
+
+/// The bucket in which project assets are stored.
+pub fn projects_bucket() -> anyhow::Result<String> {
+    std::env::var("PROJECTS_S3_BUCKET").context("PROJECTS_S3_BUCKET is not set")
+}
+
+/// Generates a unique name under which an asset of the given format can be stored.
+pub fn generate_asset_name(format: AssetFormat) -> String {
+    let rand_string: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect();
+    format!(
+        "{}_{}.{}",
+        Utc::now().timestamp(),
+        rand_string,
+        format.extension()
+    )
+}
@@ -0,0 +1,363 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::Extension;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::Stream;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::postgres::PgListener;
+use sqlx::{PgConnection, PgPool};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+use crate::Result;
+use crate::projects::{Member, ProjectAccess, ProjectRevision, Viewer};
+
+/// The Postgres channel on which committed project revisions are announced.
+const REVISION_CHANNEL: &str = "project_revisions";
+
+/// The Postgres channel on which the ids of projects moved to the trash are announced.
+const TRASH_CHANNEL: &str = "project_trash";
+
+/// The number of events that are buffered for slow subscribers.
+/// Subscribers that fall further behind skip events, which they can detect by the revision.
+const EVENT_BUFFER: usize = 64;
+
+/// The delay before reconnecting to the database after the connection to it has been lost.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// An event sent to the users that have a project open.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ProjectEvent {
+    /// A new revision of the project has been saved.
+    Change {
+        revision: i32,
+        author: String,
+        /// The modified fields of the project, along with their new values.
+        changes: Map<String, Value>,
+    },
+
+    /// The users that have the project open have changed.
+    Presence { revision: i32, viewers: Vec<Member> },
+}
+
+impl ProjectEvent {
+    fn to_sse(&self) -> Event {
+        let (name, revision) = match self {
+            ProjectEvent::Change { revision, .. } => ("change", revision),
+            ProjectEvent::Presence { revision, .. } => ("presence", revision),
+        };
+        Event::default()
+            .event(name)
+            .id(revision.to_string())
+            .json_data(self)
+            .unwrap_or_default()
+    }
+}
+
+/// A message sent to the subscribers of a project.
+#[derive(Clone, Debug)]
+enum Message {
+    Event(ProjectEvent),
+
+    /// The users that may access the project have changed,
+    /// so subscribers need to check whether they may still follow it.
+    AccessChanged,
+}
+
+/// The announcement of a committed project revision.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RevisionNotification {
+    pub project_id: Uuid,
+    pub revision: i32,
+    pub author: String,
+    pub changes: Option<Vec<String>>,
+}
+
+impl RevisionNotification {
+    /// Announces the revision once the current transaction has been committed.
+    pub(super) async fn send(&self, conn: &mut PgConnection) -> Result<()> {
+        let payload = serde_json::to_string(self).unwrap_or_default();
+        sqlx::query!("SELECT pg_notify($1, $2)", REVISION_CHANNEL, payload)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Announces that a project has been moved to the trash,
+/// once the current transaction has been committed.
+pub(super) async fn notify_trashed(conn: &mut PgConnection, project_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        "SELECT pg_notify($1, $2)",
+        TRASH_CHANNEL,
+        project_id.to_string()
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Relays changes of projects to the users that currently have them open.
+///
+/// Changes are picked up from the database, so that all ways of modifying a project are covered.
+/// Presence is tracked by each API instance on its own.
+///
+/// Subscribers are disconnected as soon as they are removed from a project,
+/// or the project is moved to the trash.
+#[derive(Clone, Default)]
+pub struct Collaboration {
+    channels: Arc<Mutex<HashMap<Uuid, Channel>>>,
+}
+
+struct Channel {
+    sender: broadcast::Sender<Message>,
+    revision: i32,
+    /// The users that have the project open, by email, along with their number of connections.
+    viewers: HashMap<String, (Member, usize)>,
+    /// The emails of the users that may access the project, as of its latest revision.
+    /// `None` until the first revision has been relayed, as subscribers are checked when joining.
+    members: Option<HashSet<String>>,
+}
+
+impl Channel {
+    fn presence(&self) -> ProjectEvent {
+        let mut viewers: Vec<Member> = self.viewers.values().map(|(it, _)| it.clone()).collect();
+        viewers.sort_by(|a, b| a.email.cmp(&b.email));
+        ProjectEvent::Presence {
+            revision: self.revision,
+            viewers,
+        }
+    }
+}
+
+/// A user's connection to a project, which ends their presence when dropped.
+struct Presence {
+    collaboration: Collaboration,
+    project_id: Uuid,
+    email: String,
+}
+
+impl Presence {
+    /// Whether the user may still access the project.
+    fn is_allowed(&self) -> bool {
+        let channels = self.collaboration.lock();
+        channels
+            .get(&self.project_id)
+            .and_then(|it| it.members.as_ref())
+            .is_none_or(|it| it.contains(&self.email))
+    }
+}
+
+impl Drop for Presence {
+    fn drop(&mut self) {
+        self.collaboration.leave(self.project_id, &self.email);
+    }
+}
+
+impl Collaboration {
+    /// Starts relaying committed project revisions in the background.
+    pub fn spawn(self, pool: PgPool) {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.relay(&pool).await {
+                    tracing::error!("Failed to relay project changes: {e:?}");
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+    }
+
+    async fn relay(&self, pool: &PgPool) -> Result<()> {
+        let mut listener = PgListener::connect_with(pool).await?;
+        listener
+            .listen_all([REVISION_CHANNEL, TRASH_CHANNEL])
+            .await?;
+        loop {
+            let notification = listener.recv().await?;
+            if notification.channel() == TRASH_CHANNEL {
+                match notification.payload().parse() {
+                    Ok(project_id) => self.restrict(project_id, HashSet::new()),
+                    Err(_) => tracing::warn!("Ignoring invalid trash notification."),
+                }
+                continue;
+            }
+            let Ok(notification) =
+                serde_json::from_str::<RevisionNotification>(notification.payload())
+            else {
+                tracing::warn!("Ignoring invalid revision notification.");
+                continue;
+            };
+            let Some(fields) = &notification.changes else {
+                continue;
+            };
+            if !self.is_open(notification.project_id) {
+                continue;
+            }
+
+            let mut conn = pool.acquire().await?;
+            let Some(project) = ProjectRevision::fetch_snapshot(
+                &mut conn,
+                notification.project_id,
+                notification.revision,
+            )
+            .await?
+            else {
+                continue;
+            };
+            // Members that have been removed must not receive the change.
+            let members = std::iter::once(&project.owner)
+                .chain(&project.editors)
+                .chain(&project.viewers)
+                .map(|it| it.email.to_lowercase())
+                .collect();
+            self.restrict(notification.project_id, members);
+
+            let Ok(Value::Object(mut values)) = serde_json::to_value(project) else {
+                continue;
+            };
+            let changes = fields
+                .iter()
+                .map(|field| (field.clone(), values.remove(field).unwrap_or(Value::Null)))
+                .collect();
+            self.publish(
+                notification.project_id,
+                notification.revision,
+                ProjectEvent::Change {
+                    revision: notification.revision,
+                    author: notification.author,
+                    changes,
+                },
+            );
+        }
+    }
+
+    fn is_open(&self, project_id: Uuid) -> bool {
+        self.lock().contains_key(&project_id)
+    }
+
+    fn publish(&self, project_id: Uuid, revision: i32, event: ProjectEvent) {
+        let mut channels = self.lock();
+        if let Some(channel) = channels.get_mut(&project_id) {
+            channel.revision = channel.revision.max(revision);
+            // Sending only fails if there are no subscribers left, which is fine.
+            let _ = channel.sender.send(Message::Event(event));
+        }
+    }
+
+    /// Limits the subscribers of a project to the users with the given emails.
+    fn restrict(&self, project_id: Uuid, members: HashSet<String>) {
+        let mut channels = self.lock();
+        if let Some(channel) = channels.get_mut(&project_id) {
+            channel.members = Some(members);
+            let _ = channel.sender.send(Message::AccessChanged);
+        }
+    }
+
+    fn join(
+        &self,
+        project_id: Uuid,
+        revision: i32,
+        member: Member,
+    ) -> (broadcast::Receiver<Message>, Presence) {
+        let mut channels = self.lock();
+        let channel = channels.entry(project_id).or_insert_with(|| Channel {
+            sender: broadcast::channel(EVENT_BUFFER).0,
+            revision,
+            viewers: HashMap::new(),
+            members: None,
+        });
+        let receiver = channel.sender.subscribe();
+        let email = member.email.to_lowercase();
+        // Access has just been checked, which may be more recent than the relayed revisions.
+        if let Some(members) = &mut channel.members {
+            members.insert(email.clone());
+        }
+        channel
+            .viewers
+            .entry(email.clone())
+            .or_insert((member, 0))
+            .1 += 1;
+        let _ = channel.sender.send(Message::Event(channel.presence()));
+
+        let presence = Presence {
+            collaboration: self.clone(),
+            project_id,
+            email,
+        };
+        (receiver, presence)
+    }
+
+    fn leave(&self, project_id: Uuid, email: &str) {
+        let mut channels = self.lock();
+        let Some(channel) = channels.get_mut(&project_id) else {
+            return;
+        };
+        if let Some((_, connections)) = channel.viewers.get_mut(email) {
+            *connections -= 1;
+            if *connections == 0 {
+                channel.viewers.remove(email);
+            }
+        }
+        if channel.viewers.is_empty() {
+            channels.remove(&project_id);
+        } else {
+            let _ = channel.sender.send(Message::Event(channel.presence()));
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, Channel>> {
+        self.channels.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Streams the changes made to a project, and who currently has it open, as server-sent events.
+///
+/// Each event carries the project revision it refers to as its id.
+/// Gaps between the revisions of `change` events mean that changes have been missed,
+/// in which case clients should reload the project.
+/// The stream ends once the user loses access to the project.
+///
+/// Like every other endpoint, this one requires an `Authorization: Bearer` header.
+/// As the browser's `EventSource` can't send headers, clients need to read the stream with
+/// a `fetch`-based SSE client instead, e.g. `@microsoft/fetch-event-source`.
+#[axum_macros::debug_handler]
+pub async fn project_events(
+    Extension(collaboration): Extension<Collaboration>,
+    access: ProjectAccess<Viewer>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let member = Member {
+        email: access.claims.email.to_lowercase(),
+        name: access.claims.given_name.clone(),
+        surname: access.claims.family_name.clone(),
+    };
+    let (receiver, presence) =
+        collaboration.join(access.project.id, access.project.revision, member);
+
+    let events = futures_util::stream::unfold(
+        (receiver, presence),
+        |(mut receiver, presence)| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(Message::Event(event)) => {
+                        return Some((Ok(event.to_sse()), (receiver, presence)));
+                    }
+                    // Skipped messages may have changed access, too.
+                    Ok(Message::AccessChanged) | Err(RecvError::Lagged(_)) => {
+                        if !presence.is_allowed() {
+                            return None;
+                        }
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        },
+    );
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,1031 @@
+use std::fmt::Write;
+use std::io::{Cursor, Read};
+
+use quick_xml::Reader;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use serde_json::Value;
+use zip::ZipArchive;
+
+use crate::projects::assets::AssetFormat;
+use crate::projects::geodesy::{Lv95, lv95_to_wgs84};
+
+/// An error that occurred while converting an uploaded asset into KML.
+/// The message is meant to be shown to the user.
+#[derive(thiserror::Error, Debug)]
+pub enum ConversionError {
+    #[error("Invalid {format} file: {message}")]
+    Invalid {
+        format: &'static str,
+        message: String,
+    },
+
+    #[error("The {0} file does not contain any features.")]
+    Empty(&'static str),
+
+    #[error("The {0} file exceeds the maximum file size once extracted.")]
+    TooLarge(&'static str),
+}
+
+impl ConversionError {
+    fn invalid(format: AssetFormat, message: impl ToString) -> Self {
+        Self::Invalid {
+            format: format.label(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Converts an asset into KML, the format in which all project assets are stored.
+///
+/// Files extracted from archives may not be larger than `max_size` bytes.
+pub fn convert_to_kml(
+    format: AssetFormat,
+    data: &[u8],
+    max_size: u64,
+) -> Result<Vec<u8>, ConversionError> {
+    let features = match format {
+        AssetFormat::Kml => {
+            validate_kml(data)?;
+            return Ok(data.to_vec());
+        }
+        AssetFormat::Kmz => return extract_kmz(data, max_size),
+        AssetFormat::GeoJson => read_geojson(data)?,
+        AssetFormat::Gpx => read_gpx(data)?,
+        AssetFormat::Csv => read_csv(data)?,
+        AssetFormat::Shapefile => read_shapefile(data, max_size)?,
+    };
+    if features.is_empty() {
+        return Err(ConversionError::Empty(format.label()));
+    }
+    Ok(write_kml(&features).into_bytes())
+}
+
+/// A named geometry, in WGS84 coordinates.
+struct Feature {
+    name: Option<String>,
+    shape: Shape,
+}
+
+enum Shape {
+    Point(Coordinate),
+    LineString(Vec<Coordinate>),
+    /// The outer ring, followed by any inner rings.
+    Polygon(Vec<Vec<Coordinate>>),
+    Multi(Vec<Shape>),
+}
+
+/// Longitude, latitude and optional height.
+#[derive(Clone, Copy)]
+struct Coordinate(f64, f64, Option<f64>);
+
+/// The coordinate reference systems in which converted assets may be provided.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Crs {
+    Wgs84,
+    Lv95,
+    Lv03,
+}
+
+impl Crs {
+    /// Guesses the reference system of a coordinate pair from its magnitude.
+    fn guess(x: f64, y: f64) -> Self {
+        if x > 2_000_000.0 && y > 1_000_000.0 {
+            Crs::Lv95
+        } else if x > 180.0 || y > 90.0 {
+            Crs::Lv03
+        } else {
+            Crs::Wgs84
+        }
+    }
+
+    /// Converts an easting/northing (or longitude/latitude) pair into WGS84.
+    /// Heights are kept as they are, as KML expects them to be above sea level.
+    fn to_wgs84(self, x: f64, y: f64, height: Option<f64>) -> Coordinate {
+        let (east, north) = match self {
+            Crs::Wgs84 => return Coordinate(x, y, height),
+            Crs::Lv95 => (x, y),
+            // LV95 coordinates only differ from LV03 ones by their false origin.
+            Crs::Lv03 => (x + 2_000_000.0, y + 1_000_000.0),
+        };
+        let position = lv95_to_wgs84(Lv95 {
+            east,
+            north,
+            height: 0.0,
+        });
+        Coordinate(position.lon, position.lat, height)
+    }
+}
+
+/// Ensures that `data` is a well-formed XML document with a `kml` root element.
+fn validate_kml(data: &[u8]) -> Result<(), ConversionError> {
+    let invalid = |message: &str| ConversionError::invalid(AssetFormat::Kml, message);
+    let mut reader = Reader::from_reader(data);
+    let mut buf = Vec::new();
+    let mut has_root = false;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e) | Event::Empty(e)) if !has_root => {
+                if e.local_name().as_ref() != b"kml" {
+                    return Err(invalid("the root element must be <kml>"));
+                }
+                has_root = true;
+            }
+            Ok(Event::Eof) if has_root => return Ok(()),
+            Ok(Event::Eof) => return Err(invalid("the document is empty")),
+            Ok(_) => {}
+            Err(err) => {
+                return Err(invalid(&format!(
+                    "{err} (at byte {})",
+                    reader.error_position()
+                )));
+            }
+        }
+        buf.clear();
+    }
+}
+
+/// Extracts the main KML document of a KMZ archive.
+fn extract_kmz(data: &[u8], max_size: u64) -> Result<Vec<u8>, ConversionError> {
+    let format = AssetFormat::Kmz;
+    let mut archive =
+        ZipArchive::new(Cursor::new(data)).map_err(|err| ConversionError::invalid(format, err))?;
+
+    // By convention, the main document is `doc.kml`, otherwise it's the first KML file.
+    let names: Vec<String> = archive.file_names().map(str::to_owned).collect();
+    let name = names
+        .iter()
+        .find(|it| it.eq_ignore_ascii_case("doc.kml"))
+        .or_else(|| names.iter().find(|it| it.to_lowercase().ends_with(".kml")))
+        .ok_or_else(|| ConversionError::invalid(format, "the archive contains no KML file"))?;
+
+    let kml = read_entry(&mut archive, name, format, max_size)?;
+    validate_kml(&kml)?;
+    Ok(kml)
+}
+
+/// Reads an entry of an archive, failing if it's larger than `max_size` bytes.
+///
+/// The size declared by the archive is not trusted,
+/// so the entry is never decompressed beyond the limit.
+fn read_entry(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+    format: AssetFormat,
+    max_size: u64,
+) -> Result<Vec<u8>, ConversionError> {
+    let entry = archive
+        .by_name(name)
+        .map_err(|err| ConversionError::invalid(format, err))?;
+    if entry.size() > max_size {
+        return Err(ConversionError::TooLarge(format.label()));
+    }
+    let mut content = Vec::new();
+    entry
+        .take(max_size + 1)
+        .read_to_end(&mut content)
+        .map_err(|err| ConversionError::invalid(format, err))?;
+    if content.len() as u64 > max_size {
+        return Err(ConversionError::TooLarge(format.label()));
+    }
+    Ok(content)
+}
+
+fn read_geojson(data: &[u8]) -> Result<Vec<Feature>, ConversionError> {
+    let format = AssetFormat::GeoJson;
+    let json: Value =
+        serde_json::from_slice(data).map_err(|err| ConversionError::invalid(format, err))?;
+
+    // GeoJSON is defined to be in WGS84, but older files may still declare a Swiss CRS.
+    let crs_name = json
+        .pointer("/crs/properties/name")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let crs = if crs_name.ends_with("2056") {
+        Crs::Lv95
+    } else if crs_name.ends_with("21781") {
+        Crs::Lv03
+    } else {
+        Crs::Wgs84
+    };
+
+    let mut features = Vec::new();
+    match json.get("type").and_then(Value::as_str) {
+        Some("FeatureCollection") => {
+            let items = json
+                .get("features")
+                .and_then(Value::as_array)
+                .ok_or_else(|| ConversionError::invalid(format, "\"features\" must be an array"))?;
+            for (i, item) in items.iter().enumerate() {
+                features.extend(read_geojson_feature(item, crs).map_err(|err| {
+                    ConversionError::invalid(format, format!("feature {i}: {err}"))
+                })?);
+            }
+        }
+        Some("Feature") => features.extend(
+            read_geojson_feature(&json, crs)
+                .map_err(|err| ConversionError::invalid(format, err))?,
+        ),
+        Some(_) => features.push(Feature {
+            name: None,
+            shape: read_geojson_geometry(&json, crs)
+                .map_err(|err| ConversionError::invalid(format, err))?,
+        }),
+        None => return Err(ConversionError::invalid(format, "missing \"type\"")),
+    }
+    Ok(features)
+}
+
+/// Reads a GeoJSON feature. Features without a geometry are skipped.
+fn read_geojson_feature(feature: &Value, crs: Crs) -> Result<Option<Feature>, String> {
+    if feature.get("type").and_then(Value::as_str) != Some("Feature") {
+        return Err("expected a \"Feature\"".to_owned());
+    }
+    let geometry = match feature.get("geometry") {
+        None | Some(Value::Null) => return Ok(None),
+        Some(geometry) => geometry,
+    };
+    let name = feature
+        .pointer("/properties/name")
+        .or_else(|| feature.pointer("/properties/Name"))
+        .or_else(|| feature.pointer("/properties/NAME"))
+        .and_then(|it| match it {
+            Value::String(it) => Some(it.clone()),
+            Value::Number(it) => Some(it.to_string()),
+            _ => None,
+        });
+    Ok(Some(Feature {
+        name,
+        shape: read_geojson_geometry(geometry, crs)?,
+    }))
+}
+
+fn read_geojson_geometry(geometry: &Value, crs: Crs) -> Result<Shape, String> {
+    let typ = geometry
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or("geometry is missing its \"type\"")?;
+    if typ == "GeometryCollection" {
+        let geometries = geometry
+            .get("geometries")
+            .and_then(Value::as_array)
+            .ok_or("\"geometries\" must be an array")?;
+        return Ok(Shape::Multi(
+            geometries
+                .iter()
+                .map(|it| read_geojson_geometry(it, crs))
+                .collect::<Result<_, _>>()?,
+        ));
+    }
+
+    let coordinates = geometry
+        .get("coordinates")
+        .ok_or_else(|| format!("{typ} is missing its \"coordinates\""))?;
+    let position = |value: &Value| -> Result<Coordinate, String> {
+        let values = value
+            .as_array()
+            .filter(|it| it.len() >= 2)
+            .and_then(|it| it.iter().map(Value::as_f64).collect::<Option<Vec<_>>>())
+            .ok_or_else(|| format!("invalid position {value}"))?;
+        Ok(crs.to_wgs84(values[0], values[1], values.get(2).copied()))
+    };
+    let array = |value: &'_ Value| -> Result<Vec<Value>, String> {
+        value
+            .as_array()
+            .cloned()
+            .ok_or_else(|| format!("invalid coordinates of {typ}"))
+    };
+    let line = |value: &Value| -> Result<Vec<Coordinate>, String> {
+        array(value)?.iter().map(position).collect()
+    };
+    let polygon = |value: &Value| -> Result<Vec<Vec<Coordinate>>, String> {
+        array(value)?.iter().map(line).collect()
+    };
+
+    let shape = match typ {
+        "Point" => Shape::Point(position(coordinates)?),
+        "LineString" => Shape::LineString(line(coordinates)?),
+        "Polygon" => Shape::Polygon(polygon(coordinates)?),
+        "MultiPoint" => Shape::Multi(
+            array(coordinates)?
+                .iter()
+                .map(|it| position(it).map(Shape::Point))
+                .collect::<Result<_, _>>()?,
+        ),
+        "MultiLineString" => Shape::Multi(
+            array(coordinates)?
+                .iter()
+                .map(|it| line(it).map(Shape::LineString))
+                .collect::<Result<_, _>>()?,
+        ),
+        "MultiPolygon" => Shape::Multi(
+            array(coordinates)?
+                .iter()
+                .map(|it| polygon(it).map(Shape::Polygon))
+                .collect::<Result<_, _>>()?,
+        ),
+        _ => return Err(format!("unsupported geometry type \"{typ}\"")),
+    };
+    Ok(shape)
+}
+
+fn read_gpx(data: &[u8]) -> Result<Vec<Feature>, ConversionError> {
+    let format = AssetFormat::Gpx;
+    let mut reader = Reader::from_reader(data);
+    reader.config_mut().trim_text(true);
+
+    let mut features = Vec::new();
+    let mut buf = Vec::new();
+
+    // The waypoint, route or track that is currently being read.
+    let mut name: Option<String> = None;
+    let mut points: Vec<Coordinate> = Vec::new();
+    let mut segments: Vec<Shape> = Vec::new();
+    let mut path: Vec<Vec<u8>> = Vec::new();
+
+    loop {
+        let event = reader.read_event_into(&mut buf).map_err(|err| {
+            ConversionError::invalid(
+                format,
+                format!("{err} (at byte {})", reader.error_position()),
+            )
+        })?;
+        match event {
+            Event::Start(e) => {
+                let tag = e.local_name().as_ref().to_vec();
+                if matches!(tag.as_slice(), b"wpt" | b"rtept" | b"trkpt") {
+                    points.push(read_gpx_point(&e)?);
+                }
+                path.push(tag);
+            }
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"wpt" => features.push(Feature {
+                    name: None,
+                    shape: Shape::Point(read_gpx_point(&e)?),
+                }),
+                b"rtept" | b"trkpt" => points.push(read_gpx_point(&e)?),
+                _ => {}
+            },
+            Event::Text(text) => {
+                let text = text
+                    .unescape()
+                    .map_err(|err| ConversionError::invalid(format, err))?;
+                let parent = path.len().checked_sub(2).map(|i| path[i].as_slice());
+                match (path.last().map(Vec::as_slice), parent) {
+                    (Some(b"name"), Some(b"wpt" | b"rte" | b"trk")) => {
+                        name = Some(text.into_owned());
+                    }
+                    (Some(b"ele"), Some(b"wpt" | b"rtept" | b"trkpt")) => {
+                        if let Some(point) = points.last_mut() {
+                            point.2 = text.trim().parse().ok();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::End(e) => {
+                path.pop();
+                match e.local_name().as_ref() {
+                    b"wpt" => {
+                        if let Some(point) = points.pop() {
+                            features.push(Feature {
+                                name: name.take(),
+                                shape: Shape::Point(point),
+                            });
+                        }
+                        points.clear();
+                    }
+                    b"rte" => {
+                        features.push(Feature {
+                            name: name.take(),
+                            shape: Shape::LineString(std::mem::take(&mut points)),
+                        });
+                    }
+                    b"trkseg" => segments.push(Shape::LineString(std::mem::take(&mut points))),
+                    b"trk" => {
+                        let shape = if segments.len() == 1 {
+                            segments.remove(0)
+                        } else {
+                            Shape::Multi(std::mem::take(&mut segments))
+                        };
+                        features.push(Feature {
+                            name: name.take(),
+                            shape,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(features)
+}
+
+fn read_gpx_point(e: &BytesStart) -> Result<Coordinate, ConversionError> {
+    let attribute = |name: &str| -> Result<f64, ConversionError> {
+        e.try_get_attribute(name)
+            .ok()
+            .flatten()
+            .and_then(|it| it.unescape_value().ok()?.trim().parse().ok())
+            .ok_or_else(|| {
+                ConversionError::invalid(
+                    AssetFormat::Gpx,
+                    format!("a point is missing a valid \"{name}\" attribute"),
+                )
+            })
+    };
+    Ok(Coordinate(attribute("lon")?, attribute("lat")?, None))
+}
+
+/// Reads a list of points from a CSV file with a header row.
+///
+/// The coordinate columns are recognized by their names,
+/// and may either be in WGS84, LV95 or LV03.
+fn read_csv(data: &[u8]) -> Result<Vec<Feature>, ConversionError> {
+    let format = AssetFormat::Csv;
+    let header = data.split(|it| *it == b'\n').next().unwrap_or_default();
+    let delimiter = [b';', b'\t', b',']
+        .into_iter()
+        .max_by_key(|delimiter| header.iter().filter(|it| *it == delimiter).count())
+        .unwrap_or(b',');
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .trim(csv::Trim::All)
+        .from_reader(data);
+
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|err| ConversionError::invalid(format, err))?
+        .iter()
+        .map(|it| it.trim_start_matches('\u{feff}').to_lowercase())
+        .collect();
+    let column = |names: &[&str]| headers.iter().position(|it| names.contains(&it.as_str()));
+    let x = column(&["lon", "lng", "long", "longitude", "x", "easting", "e"]);
+    let y = column(&["lat", "latitude", "y", "northing", "n"]);
+    let (Some(x), Some(y)) = (x, y) else {
+        return Err(ConversionError::invalid(
+            format,
+            "the header must contain a longitude/easting and a latitude/northing column",
+        ));
+    };
+    let height = column(&["height", "elevation", "ele", "alt", "altitude", "z", "h"]);
+    let name = column(&["name", "title", "label", "id"]);
+
+    let mut features = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        // The header is on the first line.
+        let line = i + 2;
+        let record = record.map_err(|err| ConversionError::invalid(format, err))?;
+        let number = |column: usize| -> Result<f64, ConversionError> {
+            let value = record.get(column).unwrap_or_default();
+            value.replace('\'', "").parse().map_err(|_| {
+                ConversionError::invalid(
+                    format,
+                    format!("line {line}: \"{value}\" is not a number"),
+                )
+            })
+        };
+        let (x, y) = (number(x)?, number(y)?);
+        let height = match height {
+            Some(column) if !record.get(column).unwrap_or_default().is_empty() => {
+                Some(number(column)?)
+            }
+            _ => None,
+        };
+        features.push(Feature {
+            name: name
+                .and_then(|it| record.get(it))
+                .filter(|it| !it.is_empty())
+                .map(str::to_owned),
+            shape: Shape::Point(Crs::guess(x, y).to_wgs84(x, y, height)),
+        });
+    }
+    Ok(features)
+}
+
+/// Reads the geometries of a zipped ESRI Shapefile.
+///
+/// Feature names are taken from the `name` attribute, if the archive contains a `.dbf` file.
+/// The reference system is taken from the `.prj` file, defaulting to WGS84.
+fn read_shapefile(data: &[u8], max_size: u64) -> Result<Vec<Feature>, ConversionError> {
+    let format = AssetFormat::Shapefile;
+    let mut archive =
+        ZipArchive::new(Cursor::new(data)).map_err(|err| ConversionError::invalid(format, err))?;
+    let names: Vec<String> = archive.file_names().map(str::to_owned).collect();
+    let shp = names
+        .iter()
+        .find(|it| it.to_lowercase().ends_with(".shp"))
+        .ok_or_else(|| ConversionError::invalid(format, "the archive contains no .shp file"))?;
+    let stem = &shp[..shp.len() - 4];
+    let sibling = |extension: &str| {
+        names.iter().find(|it| {
+            it.len() == shp.len() && it.starts_with(stem) && it.to_lowercase().ends_with(extension)
+        })
+    };
+
+    let crs = match sibling(".prj") {
+        Some(prj) => {
+            let prj = String::from_utf8_lossy(&read_entry(&mut archive, prj, format, max_size)?)
+                .to_uppercase();
+            if prj.contains("LV95") || prj.contains("CH1903+") {
+                Crs::Lv95
+            } else if prj.contains("LV03") || prj.contains("CH1903") {
+                Crs::Lv03
+            } else {
+                Crs::Wgs84
+            }
+        }
+        None => Crs::Wgs84,
+    };
+    let names = match sibling(".dbf") {
+        Some(dbf) => read_dbf_names(&read_entry(&mut archive, dbf, format, max_size)?),
+        None => Vec::new(),
+    };
+    let shapes = read_shp(&read_entry(&mut archive, shp, format, max_size)?, crs)
+        .map_err(|message| ConversionError::invalid(format, message))?;
+
+    Ok(shapes
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, shape)| {
+            Some(Feature {
+                name: names.get(i).cloned().flatten(),
+                shape: shape?,
+            })
+        })
+        .collect())
+}
+
+/// Reads the records of a `.shp` file. Null shapes are returned as `None`.
+fn read_shp(data: &[u8], crs: Crs) -> Result<Vec<Option<Shape>>, String> {
+    const HEADER_SIZE: usize = 100;
+    if data.len() < HEADER_SIZE || data[..4] != [0, 0, 0x27, 0x0a] {
+        return Err("the .shp file has an invalid header".to_owned());
+    }
+
+    let mut shapes = Vec::new();
+    let mut offset = HEADER_SIZE;
+    while offset + 8 <= data.len() {
+        let number = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap());
+        // The content length is given in 16-bit words.
+        let length = u32::from_be_bytes(data[offset + 4..offset + 8].try_into().unwrap());
+        let end = (length as usize)
+            .checked_mul(2)
+            .and_then(|it| it.checked_add(offset + 8))
+            .filter(|it| *it <= data.len())
+            .ok_or_else(|| format!("record {number} is truncated"))?;
+        shapes.push(
+            read_shp_record(&data[offset + 8..end], crs)
+                .ok_or_else(|| format!("record {number} is invalid"))?,
+        );
+        offset = end;
+    }
+    Ok(shapes)
+}
+
+fn read_shp_record(content: &[u8], crs: Crs) -> Option<Option<Shape>> {
+    let int = |at: usize| {
+        Some(i32::from_le_bytes(
+            content.get(at..at + 4)?.try_into().ok()?,
+        ))
+    };
+    let float = |at: usize| {
+        Some(f64::from_le_bytes(
+            content.get(at..at + 8)?.try_into().ok()?,
+        ))
+    };
+    let point = |at: usize| Some(crs.to_wgs84(float(at)?, float(at + 8)?, None));
+
+    let shape = match int(0)? {
+        0 => return Some(None),
+        // Point, PointZ, PointM
+        1 | 11 | 21 => Shape::Point(point(4)?),
+        // MultiPoint, MultiPointZ, MultiPointM
+        8 | 18 | 28 => {
+            let count = usize::try_from(int(36)?).ok()?;
+            Shape::Multi(
+                (0..count)
+                    .map(|i| point(40 + i * 16).map(Shape::Point))
+                    .collect::<Option<_>>()?,
+            )
+        }
+        // PolyLine, Polygon and their Z and M variants
+        typ @ (3 | 13 | 23 | 5 | 15 | 25) => {
+            let part_count = usize::try_from(int(36)?).ok()?;
+            let point_count = usize::try_from(int(40)?).ok()?;
+            let points_start = 44 + part_count * 4;
+            let mut starts = (0..part_count)
+                .map(|i| usize::try_from(int(44 + i * 4)?).ok())
+                .collect::<Option<Vec<_>>>()?;
+            starts.push(point_count);
+            let parts = starts
+                .windows(2)
+                .map(|range| {
+                    (range[0]..range[1])
+                        .map(|i| point(points_start + i * 16))
+                        .collect::<Option<Vec<_>>>()
+                })
+                .collect::<Option<Vec<_>>>()?;
+            if typ % 10 == 3 {
+                let mut lines: Vec<Shape> = parts.into_iter().map(Shape::LineString).collect();
+                if lines.len() == 1 {
+                    lines.remove(0)
+                } else {
+                    Shape::Multi(lines)
+                }
+            } else {
+                // Rings are not grouped into polygons, so each one is treated as a separate polygon.
+                let mut polygons: Vec<Shape> = parts
+                    .into_iter()
+                    .map(|it| Shape::Polygon(vec![it]))
+                    .collect();
+                if polygons.len() == 1 {
+                    polygons.remove(0)
+                } else {
+                    Shape::Multi(polygons)
+                }
+            }
+        }
+        _ => return None,
+    };
+    Some(Some(shape))
+}
+
+/// Reads the `name` attribute of each record of a `.dbf` file.
+/// Returns an empty list if the file can't be read or has no such attribute.
+fn read_dbf_names(data: &[u8]) -> Vec<Option<String>> {
+    let read = || -> Option<Vec<Option<String>>> {
+        let record_count = u32::from_le_bytes(data.get(4..8)?.try_into().ok()?) as usize;
+        let header_size = u16::from_le_bytes(data.get(8..10)?.try_into().ok()?) as usize;
+        let record_size = u16::from_le_bytes(data.get(10..12)?.try_into().ok()?) as usize;
+        if record_size == 0 {
+            return None;
+        }
+        // The declared count is not trusted, as it may exceed the records actually present.
+        let record_count = record_count.min(data.len().saturating_sub(header_size) / record_size);
+
+        // Field descriptors follow the header, and are terminated by `0x0D`.
+        // Each record starts with a deletion flag, followed by the fields' values.
+        let mut field_offset = 1;
+        let mut name_field = None;
+        for descriptor in data.get(32..header_size)?.chunks_exact(32) {
+            if descriptor[0] == 0x0d {
+                break;
+            }
+            let field_name = String::from_utf8_lossy(&descriptor[..11]);
+            let field_size = descriptor[16] as usize;
+            if field_name
+                .trim_end_matches('\0')
+                .eq_ignore_ascii_case("name")
+            {
+                name_field = Some(field_offset..field_offset + field_size);
+            }
+            field_offset += field_size;
+        }
+        let name_field = name_field?;
+
+        (0..record_count)
+            .map(|i| {
+                let record = data.get(header_size + i * record_size..)?;
+                let value = String::from_utf8_lossy(record.get(name_field.clone())?);
+                let value = value.trim();
+                Some((!value.is_empty()).then(|| value.to_owned()))
+            })
+            .collect()
+    };
+    read().unwrap_or_default()
+}
+
+fn write_kml(features: &[Feature]) -> String {
+    let mut kml = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+        "\n",
+        r#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document>"#,
+        "\n"
+    ));
+    for feature in features {
+        kml.push_str("<Placemark>");
+        if let Some(name) = &feature.name {
+            let _ = write!(kml, "<name>{}</name>", escape(name.as_str()));
+        }
+        write_kml_shape(&mut kml, &feature.shape);
+        kml.push_str("</Placemark>\n");
+    }
+    kml.push_str("</Document></kml>\n");
+    kml
+}
+
+fn write_kml_shape(kml: &mut String, shape: &Shape) {
+    fn coordinates(kml: &mut String, coordinates: &[Coordinate]) {
+        // Heights are only written if all coordinates have one.
+        let has_heights = coordinates.iter().all(|it| it.2.is_some());
+        kml.push_str("<coordinates>");
+        for (i, Coordinate(lon, lat, height)) in coordinates.iter().enumerate() {
+            if i > 0 {
+                kml.push(' ');
+            }
+            let _ = write!(kml, "{lon},{lat}");
+            if let (true, Some(height)) = (has_heights, height) {
+                let _ = write!(kml, ",{height}");
+            }
+        }
+        kml.push_str("</coordinates>");
+    }
+
+    match shape {
+        Shape::Point(point) => {
+            kml.push_str("<Point>");
+            coordinates(kml, std::slice::from_ref(point));
+            kml.push_str("</Point>");
+        }
+        Shape::LineString(points) => {
+            kml.push_str("<LineString>");
+            coordinates(kml, points);
+            kml.push_str("</LineString>");
+        }
+        Shape::Polygon(rings) => {
+            kml.push_str("<Polygon>");
+            for (i, ring) in rings.iter().enumerate() {
+                let boundary = if i == 0 {
+                    "outerBoundaryIs"
+                } else {
+                    "innerBoundaryIs"
+                };
+                let _ = write!(kml, "<{boundary}><LinearRing>");
+                coordinates(kml, ring);
+                let _ = write!(kml, "</LinearRing></{boundary}>");
+            }
+            kml.push_str("</Polygon>");
+        }
+        Shape::Multi(shapes) => {
+            kml.push_str("<MultiGeometry>");
+            for shape in shapes {
+                write_kml_shape(kml, shape);
+            }
+            kml.push_str("</MultiGeometry>");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    const MAX_SIZE: u64 = 1024 * 1024;
+
+    fn convert(format: AssetFormat, data: &[u8]) -> Result<String, ConversionError> {
+        convert_to_kml(format, data, MAX_SIZE).map(|it| String::from_utf8(it).unwrap())
+    }
+
+    fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    /// Builds a `.shp` file from the contents of its records.
+    fn shp(records: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = vec![0; 100];
+        data[..4].copy_from_slice(&[0, 0, 0x27, 0x0a]);
+        for (i, content) in records.iter().enumerate() {
+            data.extend((i as u32 + 1).to_be_bytes());
+            data.extend((content.len() as u32 / 2).to_be_bytes());
+            data.extend(content);
+        }
+        data
+    }
+
+    fn shp_point(x: f64, y: f64) -> Vec<u8> {
+        let mut content = 1i32.to_le_bytes().to_vec();
+        content.extend(x.to_le_bytes());
+        content.extend(y.to_le_bytes());
+        content
+    }
+
+    /// Builds a `.dbf` file with a single `name` field of the given size.
+    fn dbf(names: &[&str], field_size: u8) -> Vec<u8> {
+        let header_size: u16 = 32 + 32 + 1;
+        let record_size = 1 + u16::from(field_size);
+        let mut data = vec![0; 32];
+        data[0] = 3;
+        data[4..8].copy_from_slice(&(names.len() as u32).to_le_bytes());
+        data[8..10].copy_from_slice(&header_size.to_le_bytes());
+        data[10..12].copy_from_slice(&record_size.to_le_bytes());
+        let mut descriptor = [0; 32];
+        descriptor[..4].copy_from_slice(b"NAME");
+        descriptor[11] = b'C';
+        descriptor[16] = field_size;
+        data.extend(descriptor);
+        data.push(0x0d);
+        for name in names {
+            data.push(b' ');
+            data.extend(format!("{name:<width$}", width = field_size as usize).bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn kml_is_validated() {
+        let kml = br#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document/></kml>"#;
+        assert_eq!(convert(AssetFormat::Kml, kml).unwrap().as_bytes(), kml);
+        assert!(convert(AssetFormat::Kml, b"<gpx></gpx>").is_err());
+        assert!(convert(AssetFormat::Kml, b"<kml><Document></kml>").is_err());
+    }
+
+    #[test]
+    fn kmz_is_extracted() {
+        let kml = b"<kml><Document/></kml>";
+        let kmz = zip(&[("images/icon.png", b"png"), ("doc.kml", kml)]);
+        assert_eq!(convert(AssetFormat::Kmz, &kmz).unwrap().as_bytes(), kml);
+
+        let kmz = zip(&[("icon.png", b"png")]);
+        assert!(convert(AssetFormat::Kmz, &kmz).is_err());
+    }
+
+    #[test]
+    fn kmz_entries_are_limited() {
+        let kml = format!("<kml><Document>{}</Document></kml>", " ".repeat(2048));
+        let kmz = zip(&[("doc.kml", kml.as_bytes())]);
+        assert!(matches!(
+            convert_to_kml(AssetFormat::Kmz, &kmz, 1024),
+            Err(ConversionError::TooLarge(_))
+        ));
+        assert!(convert_to_kml(AssetFormat::Kmz, &kmz, kml.len() as u64).is_ok());
+    }
+
+    #[test]
+    fn geojson_is_converted() {
+        let geojson = br#"{
+            "type": "FeatureCollection",
+            "features": [
+                {"type": "Feature", "properties": {"name": "A & B"}, "geometry": {"type": "Point", "coordinates": [7.5, 46.9, 500]}},
+                {"type": "Feature", "properties": {}, "geometry": null},
+                {"type": "Feature", "properties": {"NAME": 2}, "geometry": {"type": "LineString", "coordinates": [[7, 46], [8, 47]]}}
+            ]
+        }"#;
+        let kml = convert(AssetFormat::GeoJson, geojson).unwrap();
+        assert!(
+            kml.contains("<name>A &amp; B</name><Point><coordinates>7.5,46.9,500</coordinates>")
+        );
+        assert!(kml.contains("<name>2</name><LineString><coordinates>7,46 8,47</coordinates>"));
+        assert_eq!(kml.matches("<Placemark>").count(), 2);
+    }
+
+    #[test]
+    fn geojson_in_lv95_is_converted() {
+        let geojson = br#"{
+            "type": "Point",
+            "crs": {"type": "name", "properties": {"name": "urn:ogc:def:crs:EPSG::2056"}},
+            "coordinates": [2600000, 1200000]
+        }"#;
+        let kml = convert(AssetFormat::GeoJson, geojson).unwrap();
+        assert!(kml.contains("<coordinates>7.43863"), "{kml}");
+    }
+
+    #[test]
+    fn invalid_geojson_is_rejected() {
+        let error = convert(
+            AssetFormat::GeoJson,
+            br#"{"type": "FeatureCollection", "features": [{"type": "Feature", "geometry": {"type": "Point", "coordinates": [1]}}]}"#,
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("feature 0: invalid position [1]")
+        );
+        assert!(matches!(
+            convert(
+                AssetFormat::GeoJson,
+                br#"{"type": "FeatureCollection", "features": []}"#
+            ),
+            Err(ConversionError::Empty(_))
+        ));
+    }
+
+    #[test]
+    fn gpx_is_converted() {
+        let gpx = br#"<?xml version="1.0"?>
+            <gpx version="1.1">
+                <wpt lat="46.9" lon="7.4"><name>Bern</name><ele>540</ele></wpt>
+                <trk><name>Track</name>
+                    <trkseg><trkpt lat="46" lon="7"/><trkpt lat="47" lon="8"/></trkseg>
+                </trk>
+            </gpx>"#;
+        let kml = convert(AssetFormat::Gpx, gpx).unwrap();
+        assert!(kml.contains("<name>Bern</name><Point><coordinates>7.4,46.9,540</coordinates>"));
+        assert!(kml.contains("<name>Track</name><LineString><coordinates>7,46 8,47</coordinates>"));
+
+        let error = convert(AssetFormat::Gpx, br#"<gpx><wpt lat="46"/></gpx>"#).unwrap_err();
+        assert!(error.to_string().contains("\"lon\""));
+    }
+
+    #[test]
+    fn csv_is_converted() {
+        let csv = "Name;E;N;H\nBern;2'600'000;1'200'000;540\nOther;2600000;1200000;\n";
+        let kml = convert(AssetFormat::Csv, csv.as_bytes()).unwrap();
+        assert_eq!(kml.matches("<Placemark>").count(), 2);
+        assert!(kml.contains("<name>Bern</name><Point><coordinates>7.43863"));
+        assert!(kml.contains(",540</coordinates>"));
+
+        let csv = "lon,lat\n7.5,46.9\n";
+        let kml = convert(AssetFormat::Csv, csv.as_bytes()).unwrap();
+        assert!(kml.contains("<coordinates>7.5,46.9</coordinates>"));
+
+        let error = convert(AssetFormat::Csv, b"lon,lat\n7.5,north\n").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("line 2: \"north\" is not a number")
+        );
+        assert!(convert(AssetFormat::Csv, b"a,b\n1,2\n").is_err());
+    }
+
+    #[test]
+    fn shapefile_is_converted() {
+        let shapefile = zip(&[
+            (
+                "points.shp",
+                &shp(&[shp_point(7.5, 46.9), shp_point(8.0, 47.0)]),
+            ),
+            ("points.dbf", &dbf(&["First", "Second"], 10)),
+        ]);
+        let kml = convert(AssetFormat::Shapefile, &shapefile).unwrap();
+        assert!(kml.contains("<name>First</name><Point><coordinates>7.5,46.9</coordinates>"));
+        assert!(kml.contains("<name>Second</name><Point><coordinates>8,47</coordinates>"));
+    }
+
+    #[test]
+    fn shapefile_in_lv95_is_converted() {
+        let shapefile = zip(&[
+            ("points.shp", &shp(&[shp_point(2_600_000.0, 1_200_000.0)])),
+            ("points.prj", b"PROJCS[\"CH1903+_LV95\"]"),
+        ]);
+        let kml = convert(AssetFormat::Shapefile, &shapefile).unwrap();
+        assert!(kml.contains("<coordinates>7.43863"), "{kml}");
+    }
+
+    #[test]
+    fn truncated_shp_records_are_rejected() {
+        let mut data = shp(&[shp_point(7.5, 46.9)]);
+        data.truncate(data.len() - 4);
+        assert_eq!(
+            read_shp(&data, Crs::Wgs84).err().as_deref(),
+            Some("record 1 is truncated")
+        );
+
+        // A length that overflows the record's end offset.
+        let mut data = shp(&[shp_point(7.5, 46.9)]);
+        data[104..108].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(
+            read_shp(&data, Crs::Wgs84).err().as_deref(),
+            Some("record 1 is truncated")
+        );
+
+        // A record that declares more points than it contains.
+        let mut content = 8i32.to_le_bytes().to_vec();
+        content.extend([0; 32]);
+        content.extend(i32::MAX.to_le_bytes());
+        assert_eq!(
+            read_shp(&shp(&[content]), Crs::Wgs84).err().as_deref(),
+            Some("record 1 is invalid")
+        );
+
+        assert!(read_shp(b"not a shapefile", Crs::Wgs84).is_err());
+    }
+
+    #[test]
+    fn dbf_names_are_read() {
+        assert_eq!(
+            read_dbf_names(&dbf(&["First", "", "Third"], 8)),
+            vec![Some("First".to_owned()), None, Some("Third".to_owned())]
+        );
+    }
+
+    #[test]
+    fn malformed_dbf_files_are_ignored() {
+        // A record size of zero.
+        let mut data = dbf(&["First"], 8);
+        data[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        data[10..12].copy_from_slice(&0u16.to_le_bytes());
+        assert!(read_dbf_names(&data).is_empty());
+
+        // More records than the file contains.
+        let mut data = dbf(&["First"], 8);
+        data[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(read_dbf_names(&data), vec![Some("First".to_owned())]);
+
+        assert!(read_dbf_names(b"short").is_empty());
+    }
+}
@@ -0,0 +1,59 @@
+use axum::http::header::IF_MATCH;
+use axum::http::{HeaderValue, StatusCode};
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+
+use crate::Error;
+use crate::projects::Project;
+
+/// The project revision that a client bases its modifications on,
+/// taken from the request's `If-Match` header.
+///
+/// The header is expected to contain an entity tag as returned by [etag].
+/// If the header is absent or `*`, the client did not specify a revision.
+pub struct IfMatch(pub Option<i32>);
+
+impl IfMatch {
+    /// Ensures that the revision expected by the client is the one of `project`.
+    /// Returns the revision on which modifications of `project` need to be based.
+    pub fn check(&self, project: &Project) -> Result<i32, Error> {
+        match self.0 {
+            Some(revision) if revision != project.revision => Err(conflict()),
+            _ => Ok(project.revision),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IfMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(value) = parts.headers.get(IF_MATCH) else {
+            return Ok(Self(None));
+        };
+        let invalid = || Error::Api(StatusCode::BAD_REQUEST, "Invalid If-Match header.");
+        let value = value.to_str().map_err(|_| invalid())?.trim();
+        if value == "*" {
+            return Ok(Self(None));
+        }
+        let value = value.strip_prefix("W/").unwrap_or(value);
+        let revision = value.trim_matches('"').parse().map_err(|_| invalid())?;
+        Ok(Self(Some(revision)))
+    }
+}
+
+/// Creates the entity tag identifying a specific project revision.
+pub fn etag(revision: i32) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{revision}\"")).expect("valid etag")
+}
+
+/// The error returned when a project has been modified since the client last fetched it.
+pub fn conflict() -> Error {
+    Error::Api(
+        StatusCode::CONFLICT,
+        "Project has been modified in the meantime.",
+    )
+}
@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use anyhow::Context;
+use aws_sdk_s3::Client;
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::projects::assets::projects_bucket;
+
+/// Configuration for the garbage collection of project assets.
+#[derive(clap::Parser, Debug, Clone)]
+pub struct AssetGc {
+    /// The time after which uploaded assets that have not been saved to a project are deleted,
+    /// in seconds. Saved assets are only deleted once they're unreferenced for at least as long.
+    #[clap(long, env, default_value = "86400")]
+    pub asset_gc_ttl: u64,
+
+    /// The interval at which the garbage collection runs in the background, in seconds.
+    /// If not set or zero, it only runs when started with `--gc-assets`.
+    #[clap(long, env)]
+    pub asset_gc_interval: Option<u64>,
+
+    /// Only report the assets that would be deleted, without deleting them.
+    #[clap(long, env)]
+    pub asset_gc_dry_run: bool,
+}
+
+/// The outcome of a garbage collection run.
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetGcReport {
+    /// Whether the run only reported the assets, without deleting them.
+    pub dry_run: bool,
+
+    /// The keys of uploaded assets that have expired before being saved to a project.
+    pub expired_uploads: Vec<String>,
+
+    /// The keys of saved assets that are no longer referenced by any project or revision.
+    pub orphaned_assets: Vec<String>,
+
+    /// The combined size of the collected assets, in bytes.
+    pub size: u64,
+}
+
+impl AssetGc {
+    /// Deletes expired uploads and orphaned assets from the bucket.
+    pub async fn run(&self, pool: &PgPool, client: &Client) -> anyhow::Result<AssetGcReport> {
+        let bucket = projects_bucket()?;
+        let cutoff = Utc::now().timestamp() - self.asset_gc_ttl as i64;
+
+        // Assets may still be restored from past revisions, so they count as references too.
+        let referenced: HashSet<String> = sqlx::query_scalar!(
+            r#"
+            SELECT key AS "key!" FROM project_assets
+            UNION
+            SELECT asset->>'key' AS "key!"
+            FROM project_revisions, jsonb_array_elements(snapshot->'assets') AS asset
+            "#
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|key| format!("assets/saved/{key}"))
+        .collect();
+
+        let mut report = AssetGcReport {
+            dry_run: self.asset_gc_dry_run,
+            ..Default::default()
+        };
+        for (key, size) in list_objects(client, &bucket, "assets/temp/", cutoff).await? {
+            report.expired_uploads.push(key);
+            report.size += size;
+        }
+        // Saved assets also need to have expired, as they're stored before their project is.
+        for (key, size) in list_objects(client, &bucket, "assets/saved/", cutoff).await? {
+            if !referenced.contains(&key) {
+                report.orphaned_assets.push(key);
+                report.size += size;
+            }
+        }
+
+        if !self.asset_gc_dry_run {
+            for key in report.expired_uploads.iter().chain(&report.orphaned_assets) {
+                client
+                    .delete_object()
+                    .bucket(&bucket)
+                    .key(key)
+                    .send()
+                    .await
+                    .with_context(|| format!("Failed to delete asset \"{key}\""))?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Runs the garbage collection at the configured interval, if any.
+    pub fn spawn(self, pool: PgPool, client: Client) {
+        let Some(interval) = self.asset_gc_interval.filter(|it| *it > 0) else {
+            return;
+        };
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(interval));
+            loop {
+                interval.tick().await;
+                match self.run(&pool, &client).await {
+                    Ok(report) => report.log(),
+                    Err(err) => tracing::error!("Asset garbage collection failed: {err:?}"),
+                }
+            }
+        });
+    }
+}
+
+impl AssetGcReport {
+    /// Writes the collected assets and a summary of the run to the log.
+    pub fn log(&self) {
+        let action = if self.dry_run {
+            "Would delete"
+        } else {
+            "Deleted"
+        };
+        for key in self.expired_uploads.iter().chain(&self.orphaned_assets) {
+            tracing::debug!("{action} asset \"{key}\".");
+        }
+        tracing::info!(
+            "{action} {uploads} expired uploads and {orphans} orphaned assets, totalling {size} bytes.",
+            uploads = self.expired_uploads.len(),
+            orphans = self.orphaned_assets.len(),
+            size = self.size,
+        );
+    }
+}
+
+/// Lists the keys and sizes of all objects below `prefix` that were last modified before `cutoff`.
+pub(super) async fn list_objects(
+    client: &Client,
+    bucket: &str,
+    prefix: &str,
+    cutoff: i64,
+) -> anyhow::Result<Vec<(String, u64)>> {
+    let mut objects = Vec::new();
+    let mut pages = client
+        .list_objects_v2()
+        .bucket(bucket)
+        .prefix(prefix)
+        .into_paginator()
+        .send();
+    while let Some(page) = pages.next().await {
+        let page = page.with_context(|| format!("Failed to list assets in \"{prefix}\""))?;
+        for object in page.contents() {
+            let (Some(key), Some(modified)) = (object.key(), object.last_modified()) else {
+                continue;
+            };
+            if modified.secs() < cutoff {
+                let size = object.size().unwrap_or_default().max(0) as u64;
+                objects.push((key.to_owned(), size));
+            }
+        }
+    }
+    Ok(objects)
+}
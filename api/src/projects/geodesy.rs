@@ -0,0 +1,213 @@
+//! Conversions between the coordinate reference systems used by projects.
+//!
+//! Geometries are stored as ECEF (Earth-Centered, Earth-Fixed) coordinates, as used by Cesium.
+//! The Swiss reference frames are converted with the approximate formulas published by swisstopo,
+//! which are accurate to about a meter.
+
+use serde_json::Number;
+
+use crate::projects::Cartesian3;
+
+/// The semi-major axis of the WGS84 ellipsoid, in meters.
+const WGS84_A: f64 = 6_378_137.0;
+
+/// The flattening of the WGS84 ellipsoid.
+const WGS84_F: f64 = 1.0 / 298.257_223_563;
+
+/// A position on the WGS84 ellipsoid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wgs84 {
+    /// The longitude, in degrees.
+    pub lon: f64,
+    /// The latitude, in degrees.
+    pub lat: f64,
+    /// The height above the ellipsoid, in meters.
+    pub height: f64,
+}
+
+/// A position in the Swiss LV95 frame, with heights in LN02.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lv95 {
+    pub east: f64,
+    pub north: f64,
+    /// The height above sea level, in meters.
+    pub height: f64,
+}
+
+/// Converts ECEF coordinates into WGS84, using Bowring's method.
+pub fn ecef_to_wgs84(x: f64, y: f64, z: f64) -> Wgs84 {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let b = WGS84_A * (1.0 - WGS84_F);
+    let ep2 = (WGS84_A.powi(2) - b.powi(2)) / b.powi(2);
+
+    let p = x.hypot(y);
+    let theta = (z * WGS84_A).atan2(p * b);
+    let lat = (z + ep2 * b * theta.sin().powi(3)).atan2(p - e2 * WGS84_A * theta.cos().powi(3));
+    let n = WGS84_A / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+    // Near the poles, the height is derived from `z` to not divide by a vanishing cosine.
+    let height = if lat.abs() < std::f64::consts::FRAC_PI_4 {
+        p / lat.cos() - n
+    } else {
+        z / lat.sin() - n * (1.0 - e2)
+    };
+    Wgs84 {
+        lon: y.atan2(x).to_degrees(),
+        lat: lat.to_degrees(),
+        height,
+    }
+}
+
+/// Converts WGS84 coordinates into LV95/LN02.
+pub fn wgs84_to_lv95(position: Wgs84) -> Lv95 {
+    let lat_aux = (position.lat * 3600.0 - 169_028.66) / 10_000.0;
+    let lon_aux = (position.lon * 3600.0 - 26_782.5) / 10_000.0;
+    let east = 2_600_072.37 + 211_455.93 * lon_aux
+        - 10_938.51 * lon_aux * lat_aux
+        - 0.36 * lon_aux * lat_aux.powi(2)
+        - 44.54 * lon_aux.powi(3);
+    let north =
+        1_200_147.07 + 308_807.95 * lat_aux + 3_745.25 * lon_aux.powi(2) + 76.63 * lat_aux.powi(2)
+            - 194.56 * lon_aux.powi(2) * lat_aux
+            + 119.79 * lat_aux.powi(3);
+    let height = position.height - 49.55 + 2.73 * lon_aux + 6.94 * lat_aux;
+    Lv95 {
+        east,
+        north,
+        height,
+    }
+}
+
+/// Converts LV95/LN02 coordinates into WGS84.
+pub fn lv95_to_wgs84(position: Lv95) -> Wgs84 {
+    let y_aux = (position.east - 2_600_000.0) / 1_000_000.0;
+    let x_aux = (position.north - 1_200_000.0) / 1_000_000.0;
+    let lon = 2.677_909_4 + 4.728_982 * y_aux + 0.791_484 * y_aux * x_aux - 0.0436 * y_aux.powi(3)
+        + 0.1306 * y_aux * x_aux.powi(2);
+    let lat = 16.902_389_2 + 3.238_272 * x_aux
+        - 0.270_978 * y_aux.powi(2)
+        - 0.002_528 * x_aux.powi(2)
+        - 0.0447 * y_aux.powi(2) * x_aux
+        - 0.0140 * x_aux.powi(3);
+    let height = position.height + 49.55 - 12.60 * y_aux - 22.64 * x_aux;
+    Wgs84 {
+        lon: lon * 100.0 / 36.0,
+        lat: lat * 100.0 / 36.0,
+        height,
+    }
+}
+
+/// Converts WGS84 coordinates into ECEF.
+pub fn wgs84_to_ecef(position: Wgs84) -> [f64; 3] {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let (lon, lat) = (position.lon.to_radians(), position.lat.to_radians());
+    let n = WGS84_A / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+    [
+        (n + position.height) * lat.cos() * lon.cos(),
+        (n + position.height) * lat.cos() * lon.sin(),
+        (n * (1.0 - e2) + position.height) * lat.sin(),
+    ]
+}
+
+impl Cartesian3 {
+    /// Creates a Cesium position from WGS84 coordinates.
+    pub(super) fn from_wgs84(position: Wgs84) -> Option<Self> {
+        let [x, y, z] = wgs84_to_ecef(position).map(Number::from_f64);
+        Some(Cartesian3 {
+            x: x?,
+            y: y?,
+            z: z?,
+        })
+    }
+
+    pub(super) fn to_wgs84(&self) -> Wgs84 {
+        ecef_to_wgs84(
+            self.x.as_f64().unwrap_or_default(),
+            self.y.as_f64().unwrap_or_default(),
+            self.z.as_f64().unwrap_or_default(),
+        )
+    }
+
+    pub(super) fn to_lv95(&self) -> Lv95 {
+        wgs84_to_lv95(self.to_wgs84())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {expected} ± {tolerance}, got {actual}"
+        );
+    }
+
+    /// Converts degrees, minutes and seconds into decimal degrees.
+    fn dms(degrees: f64, minutes: f64, seconds: f64) -> f64 {
+        degrees + minutes / 60.0 + seconds / 3600.0
+    }
+
+    #[test]
+    fn lv95_origin_is_bern() {
+        let position = lv95_to_wgs84(Lv95 {
+            east: 2_600_000.0,
+            north: 1_200_000.0,
+            height: 0.0,
+        });
+        assert_close(position.lon, 7.438_632, 1e-5);
+        assert_close(position.lat, 46.951_083, 1e-5);
+    }
+
+    // The reference points of swisstopo's "Approximate formulas for the transformation
+    // between Swiss projection coordinates and WGS84".
+    #[test]
+    fn lv95_is_converted_to_wgs84() {
+        let position = lv95_to_wgs84(Lv95 {
+            east: 2_700_000.0,
+            north: 1_100_000.0,
+            height: 600.0,
+        });
+        assert_close(position.lon, dms(8.0, 43.0, 49.80), 1e-5);
+        assert_close(position.lat, dms(46.0, 2.0, 38.86), 1e-5);
+        assert_close(position.height, 650.55, 0.01);
+    }
+
+    #[test]
+    fn wgs84_is_converted_to_lv95() {
+        let position = wgs84_to_lv95(Wgs84 {
+            lon: dms(8.0, 43.0, 49.79),
+            lat: dms(46.0, 2.0, 38.87),
+            height: 650.60,
+        });
+        assert_close(position.east, 2_699_999.76, 0.01);
+        assert_close(position.north, 1_099_999.97, 0.01);
+        assert_close(position.height, 600.05, 0.01);
+    }
+
+    #[test]
+    fn ecef_is_converted_to_wgs84() {
+        let position = ecef_to_wgs84(WGS84_A, 0.0, 0.0);
+        assert_close(position.lon, 0.0, 1e-12);
+        assert_close(position.lat, 0.0, 1e-12);
+        assert_close(position.height, 0.0, 1e-6);
+
+        let position = ecef_to_wgs84(0.0, 0.0, WGS84_A * (1.0 - WGS84_F) + 100.0);
+        assert_close(position.lat, 90.0, 1e-12);
+        assert_close(position.height, 100.0, 1e-6);
+    }
+
+    #[test]
+    fn wgs84_round_trips_through_ecef() {
+        let position = Wgs84 {
+            lon: 7.438_632,
+            lat: 46.951_083,
+            height: 549.5,
+        };
+        let [x, y, z] = wgs84_to_ecef(position);
+        let result = ecef_to_wgs84(x, y, z);
+        assert_close(result.lon, position.lon, 1e-9);
+        assert_close(result.lat, position.lat, 1e-9);
+        assert_close(result.height, position.height, 1e-3);
+    }
+}
@@ -0,0 +1,275 @@
+use axum::extract::{Extension, Json, Query};
+use axum::http::header::{CONTENT_TYPE, ETAG};
+use axum::http::{HeaderValue, StatusCode};
+use axum::response::IntoResponse;
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use sqlx::PgPool;
+
+use crate::projects::geodesy::{Lv95, Wgs84, lv95_to_wgs84};
+use crate::projects::{
+    Cartesian3, Editor, Geometry, GeometryType, IfMatch, Project, ProjectAccess, Viewer, etag,
+};
+use crate::validation::{FieldError, field, validate};
+use crate::{Error, Result};
+
+/// The media type of GeoJSON documents.
+const GEOJSON_CONTENT_TYPE: &str = "application/geo+json";
+
+/// The name under which LV95 is declared in a GeoJSON `crs` member.
+const LV95_CRS_NAME: &str = "urn:ogc:def:crs:EPSG::2056";
+
+/// The coordinate reference systems in which geometries can be exchanged as GeoJSON.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GeoJsonCrs {
+    /// WGS84 longitude, latitude and ellipsoidal height, as required by RFC 7946.
+    #[default]
+    Wgs84,
+    /// LV95 easting and northing, with LN02 heights.
+    Lv95,
+}
+
+#[derive(Deserialize)]
+pub struct CrsQuery {
+    /// Defaults to the CRS declared by the document itself, or WGS84.
+    crs: Option<GeoJsonCrs>,
+}
+
+impl GeoJsonCrs {
+    /// Detects the CRS declared by the legacy `crs` member of a GeoJSON document.
+    fn declared_by(document: &Value) -> Option<Self> {
+        let name = document.pointer("/crs/properties/name")?.as_str()?;
+        if name.ends_with("2056") {
+            Some(GeoJsonCrs::Lv95)
+        } else if name.ends_with("4326") || name.ends_with("CRS84") {
+            Some(GeoJsonCrs::Wgs84)
+        } else {
+            None
+        }
+    }
+
+    fn position(&self, position: &Cartesian3) -> Value {
+        match self {
+            GeoJsonCrs::Wgs84 => {
+                let wgs84 = position.to_wgs84();
+                json!([wgs84.lon, wgs84.lat, wgs84.height])
+            }
+            GeoJsonCrs::Lv95 => {
+                let lv95 = position.to_lv95();
+                json!([lv95.east, lv95.north, lv95.height])
+            }
+        }
+    }
+
+    /// Converts GeoJSON coordinates into a Cesium position.
+    /// Positions without a height are placed at height zero.
+    fn cartesian(&self, coordinates: &Value) -> Option<Cartesian3> {
+        let values = coordinates
+            .as_array()?
+            .iter()
+            .map(Value::as_f64)
+            .collect::<Option<Vec<f64>>>()?;
+        let (a, b, height) = match values[..] {
+            [a, b] => (a, b, 0.0),
+            [a, b, height] => (a, b, height),
+            _ => return None,
+        };
+        let wgs84 = match self {
+            GeoJsonCrs::Wgs84 => Wgs84 {
+                lon: a,
+                lat: b,
+                height,
+            },
+            GeoJsonCrs::Lv95 => lv95_to_wgs84(Lv95 {
+                east: a,
+                north: b,
+                height,
+            }),
+        };
+        Cartesian3::from_wgs84(wgs84)
+    }
+}
+
+impl Geometry {
+    /// Converts the geometry into a GeoJSON feature.
+    /// All of the geometry's attributes that are set, apart from its positions, become the feature's properties.
+    pub fn to_geojson(&self, crs: GeoJsonCrs) -> Value {
+        let positions: Vec<Value> = self.positions.iter().map(|it| crs.position(it)).collect();
+        let geometry = match (&self.typ, positions.len()) {
+            (_, 0) => Value::Null,
+            (GeometryType::Point, 1) => json!({ "type": "Point", "coordinates": positions[0] }),
+            (GeometryType::Point, _) => json!({ "type": "MultiPoint", "coordinates": positions }),
+            (GeometryType::Polygon | GeometryType::Rectangle, _) => {
+                // GeoJSON requires rings to be closed.
+                let mut ring = positions;
+                if ring.first() != ring.last() {
+                    ring.push(ring[0].clone());
+                }
+                json!({ "type": "Polygon", "coordinates": [ring] })
+            }
+            _ => json!({ "type": "LineString", "coordinates": positions }),
+        };
+
+        let mut properties = serde_json::to_value(self).unwrap_or_default();
+        if let Some(properties) = properties.as_object_mut() {
+            properties.remove("positions");
+            properties.retain(|_, value| !value.is_null());
+        }
+        json!({
+            "type": "Feature",
+            "id": self.id,
+            "geometry": geometry,
+            "properties": properties,
+        })
+    }
+}
+
+impl Project {
+    /// Converts the project's geometries into a GeoJSON feature collection.
+    pub fn geometries_to_geojson(&self, crs: GeoJsonCrs) -> Value {
+        let features: Vec<Value> = self
+            .geometries
+            .iter()
+            .map(|it| it.to_geojson(crs))
+            .collect();
+        let mut collection = json!({
+            "type": "FeatureCollection",
+            "name": self.title,
+            "features": features,
+        });
+        // Non-WGS84 coordinates need to be declared, which is only supported by the legacy spec.
+        if crs == GeoJsonCrs::Lv95 {
+            collection["crs"] = json!({
+                "type": "name",
+                "properties": { "name": LV95_CRS_NAME },
+            });
+        }
+        collection
+    }
+}
+
+impl Geometry {
+    /// Creates a geometry from a GeoJSON feature, as created by [Geometry::to_geojson].
+    ///
+    /// If the feature's properties don't specify the geometry's type,
+    /// it is derived from the type of the feature's geometry.
+    /// Errors in the feature's properties are reported relative to `path`.
+    pub fn from_geojson(feature: &Value, crs: GeoJsonCrs, path: &str) -> Result<Self> {
+        let invalid = || Error::Api(StatusCode::BAD_REQUEST, "Invalid GeoJSON feature.");
+        if feature["type"] != "Feature" {
+            return Err(invalid());
+        }
+        let geometry = &feature["geometry"];
+        let coordinates = &geometry["coordinates"];
+        let (typ, mut coordinates) = match geometry["type"].as_str() {
+            Some("Point") => (GeometryType::Point, vec![coordinates]),
+            Some("MultiPoint") => (GeometryType::Point, as_vec(coordinates)),
+            Some("LineString") => (GeometryType::Line, as_vec(coordinates)),
+            Some("Polygon") => (
+                GeometryType::Polygon,
+                coordinates.get(0).map(as_vec).unwrap_or_default(),
+            ),
+            _ => return Err(invalid()),
+        };
+        // Rings are stored without repeating their first position.
+        if typ == GeometryType::Polygon
+            && coordinates.len() > 1
+            && coordinates.first() == coordinates.last()
+        {
+            coordinates.pop();
+        }
+        let positions = coordinates
+            .into_iter()
+            .map(|it| crs.cartesian(it))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+
+        let mut properties = match &feature["properties"] {
+            Value::Object(properties) => properties.clone(),
+            Value::Null => Map::new(),
+            _ => return Err(invalid()),
+        };
+        if !properties.contains_key("type") {
+            properties.insert(
+                "type".to_owned(),
+                serde_json::to_value(typ).map_err(|_| invalid())?,
+            );
+        }
+        if let Some(id) = feature["id"].as_str() {
+            properties.entry("id").or_insert_with(|| Value::from(id));
+        }
+        properties.insert(
+            "positions".to_owned(),
+            serde_json::to_value(positions).map_err(|_| invalid())?,
+        );
+        serde_path_to_error::deserialize(Value::Object(properties)).map_err(|e| {
+            let field = field(&field(path, "properties"), &e.path().to_string());
+            Error::Validation(vec![FieldError::new(field, e.into_inner().to_string())])
+        })
+    }
+}
+
+fn as_vec(value: &Value) -> Vec<&Value> {
+    value
+        .as_array()
+        .map(|it| it.iter().collect())
+        .unwrap_or_default()
+}
+
+/// Returns the geometries of a project as a GeoJSON feature collection.
+#[axum_macros::debug_handler]
+pub async fn get_project_geojson(
+    access: ProjectAccess<Viewer>,
+    Query(query): Query<CrsQuery>,
+) -> impl IntoResponse {
+    let project = access.project;
+    let collection = project.geometries_to_geojson(query.crs.unwrap_or_default());
+    (
+        [
+            (ETAG, etag(project.revision)),
+            (CONTENT_TYPE, HeaderValue::from_static(GEOJSON_CONTENT_TYPE)),
+        ],
+        Json(collection),
+    )
+}
+
+/// Replaces the geometries of a project with the features of a GeoJSON feature collection.
+#[axum_macros::debug_handler]
+pub async fn update_project_geojson(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Editor>,
+    if_match: IfMatch,
+    Query(query): Query<CrsQuery>,
+    Json(collection): Json<Value>,
+) -> Result<impl IntoResponse> {
+    let mut project = access.project;
+    let revision = if_match.check(&project)?;
+
+    if collection["type"] != "FeatureCollection" {
+        return Err(Error::Api(
+            StatusCode::BAD_REQUEST,
+            "Expected a GeoJSON feature collection.",
+        ));
+    }
+    let crs = query
+        .crs
+        .or_else(|| GeoJsonCrs::declared_by(&collection))
+        .unwrap_or_default();
+    project.geometries = as_vec(&collection["features"])
+        .into_iter()
+        .enumerate()
+        .map(|(i, it)| Geometry::from_geojson(it, crs, &format!("features[{i}]")))
+        .collect::<Result<_>>()?;
+    validate(&project.geometries, "features")?;
+    project.modified = Some(Utc::now());
+
+    let mut tx = pool.begin().await?;
+    project
+        .update(&mut tx, revision, &access.claims.email)
+        .await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::NO_CONTENT, [(ETAG, etag(project.revision))]))
+}
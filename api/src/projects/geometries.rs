@@ -0,0 +1,177 @@
+use axum::extract::{Extension, Json, Path};
+use axum::http::StatusCode;
+use axum::http::header::ETAG;
+use axum::response::IntoResponse;
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::projects::{Editor, Geometry, IfMatch, Project, ProjectAccess, Viewer, etag};
+use crate::validation::ValidJson;
+use crate::{Error, Result};
+
+#[derive(Deserialize)]
+pub struct GeometryPath {
+    geometry: String,
+}
+
+#[derive(Deserialize)]
+pub struct MoveGeometry {
+    /// The index that the geometry is moved to.
+    /// Indices past the end move the geometry to the end.
+    pub position: usize,
+}
+
+impl Project {
+    /// Gives each geometry lacking an id, or sharing it with a preceding geometry, a new one.
+    /// Existing ids are kept, so that they stay stable across saves.
+    pub(super) fn assign_geometry_ids(&mut self) {
+        let mut ids = HashSet::new();
+        for geometry in &mut self.geometries {
+            let is_unique = geometry
+                .id
+                .as_ref()
+                .is_some_and(|id| !id.is_empty() && ids.insert(id.clone()));
+            if !is_unique {
+                let id = Uuid::new_v4().to_string();
+                ids.insert(id.clone());
+                geometry.id = Some(id);
+            }
+        }
+    }
+
+    fn geometry_index(&self, id: &str) -> Result<usize> {
+        self.geometries
+            .iter()
+            .position(|it| it.id.as_deref() == Some(id))
+            .ok_or(Error::NotFound)
+    }
+}
+
+#[axum_macros::debug_handler]
+pub async fn get_geometry(
+    access: ProjectAccess<Viewer>,
+    Path(GeometryPath { geometry }): Path<GeometryPath>,
+) -> Result<impl IntoResponse> {
+    let mut project = access.project;
+    let index = project.geometry_index(&geometry)?;
+    let geometry = project.geometries.swap_remove(index);
+    Ok(([(ETAG, etag(project.revision))], Json(geometry)))
+}
+
+/// Adds a geometry to the end of a project's geometries.
+/// The geometry is assigned a new id, regardless of the one it was sent with.
+#[axum_macros::debug_handler]
+pub async fn create_geometry(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Editor>,
+    if_match: IfMatch,
+    ValidJson(mut geometry): ValidJson<Geometry>,
+) -> Result<impl IntoResponse> {
+    let id = Uuid::new_v4().to_string();
+    geometry.id = Some(id.clone());
+    let project = change(&pool, &access, &if_match, |project| {
+        project.geometries.push(geometry);
+        Ok(())
+    })
+    .await?;
+
+    let geometry = project.geometries[project.geometry_index(&id)?].clone();
+    Ok((
+        StatusCode::CREATED,
+        [(ETAG, etag(project.revision))],
+        Json(geometry),
+    ))
+}
+
+/// Replaces a single geometry, keeping its id and position.
+#[axum_macros::debug_handler]
+pub async fn update_geometry(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Editor>,
+    if_match: IfMatch,
+    Path(GeometryPath { geometry: id }): Path<GeometryPath>,
+    ValidJson(mut geometry): ValidJson<Geometry>,
+) -> Result<impl IntoResponse> {
+    geometry.id = Some(id.clone());
+    let project = change(&pool, &access, &if_match, |project| {
+        let index = project.geometry_index(&id)?;
+        project.geometries[index] = geometry;
+        Ok(())
+    })
+    .await?;
+
+    let geometry = project.geometries[project.geometry_index(&id)?].clone();
+    Ok(([(ETAG, etag(project.revision))], Json(geometry)))
+}
+
+#[axum_macros::debug_handler]
+pub async fn delete_geometry(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Editor>,
+    if_match: IfMatch,
+    Path(GeometryPath { geometry: id }): Path<GeometryPath>,
+) -> Result<impl IntoResponse> {
+    let project = change(&pool, &access, &if_match, |project| {
+        let index = project.geometry_index(&id)?;
+        project.geometries.remove(index);
+        Ok(())
+    })
+    .await?;
+    Ok((StatusCode::NO_CONTENT, [(ETAG, etag(project.revision))]))
+}
+
+/// Moves a single geometry to another position within the project's geometries.
+#[axum_macros::debug_handler]
+pub async fn move_geometry(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Editor>,
+    if_match: IfMatch,
+    Path(GeometryPath { geometry: id }): Path<GeometryPath>,
+    Json(request): Json<MoveGeometry>,
+) -> Result<impl IntoResponse> {
+    let project = change(&pool, &access, &if_match, |project| {
+        let index = project.geometry_index(&id)?;
+        let geometry = project.geometries.remove(index);
+        let position = request.position.min(project.geometries.len());
+        project.geometries.insert(position, geometry);
+        Ok(())
+    })
+    .await?;
+    Ok((StatusCode::NO_CONTENT, [(ETAG, etag(project.revision))]))
+}
+
+/// Applies a change to the latest stored version of a project and saves it as a new revision.
+///
+/// The project is locked while being changed. Unless the client expects a specific revision,
+/// changes made concurrently to other geometries are therefore kept instead of being
+/// overwritten or answered with `409 Conflict`.
+async fn change(
+    pool: &PgPool,
+    access: &ProjectAccess<Editor>,
+    if_match: &IfMatch,
+    change: impl FnOnce(&mut Project) -> Result<()>,
+) -> Result<Project> {
+    let mut tx = pool.begin().await?;
+    sqlx::query!(
+        "SELECT id FROM projects WHERE id = $1 FOR UPDATE",
+        access.project.id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+    let mut project = Project::fetch(&mut tx, access.project.id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let revision = if_match.check(&project)?;
+
+    change(&mut project)?;
+    project.modified = Some(Utc::now());
+    project
+        .update(&mut tx, revision, &access.claims.email)
+        .await?;
+    tx.commit().await?;
+    Ok(project)
+}
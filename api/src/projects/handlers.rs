@@ -0,0 +1,240 @@
+use aws_sdk_s3::Client;
+use axum::{
+    extract::{Extension, Json, Query},
+    http::{StatusCode, header::ETAG},
+    response::IntoResponse,
+};
+use chrono::Utc;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::audit::{AuditAction, AuditEntry, AuditTarget};
+use crate::auth::Claims;
+use crate::projects::assets::{AssetLimits, copy_assets, ensure_within_quota, save_assets};
+use crate::projects::search::ProjectQuery;
+use crate::projects::{
+    CreateProject, Editor, Geometry, IfMatch, Owner, Project, ProjectAccess, ProjectRole, Viewer,
+    etag,
+};
+use crate::validation::ValidJson;
+use crate::{Error, Result};
+use axum_macros::debug_handler;
+
+#[debug_handler]
+pub async fn create_project(
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    Extension(limits): Extension<Arc<AssetLimits>>,
+    claims: Claims,
+    ValidJson(mut project): ValidJson<CreateProject>,
+) -> Result<Json<Uuid>> {
+    // Sanity check
+    if project.owner.email.to_lowercase() != claims.email.to_lowercase() {
+        return Err(Error::Api(
+            StatusCode::BAD_REQUEST,
+            "Project owner does not match token claims.",
+        ));
+    }
+
+    ensure_within_quota(&client, &limits, &project.assets).await?;
+    save_assets(&client, &limits, &mut project.assets).await?;
+
+    // Create project
+    let mut project = Project {
+        id: Uuid::new_v4(),
+        title: project.title,
+        description: project.description,
+        created: Utc::now(),
+        modified: None,
+        revision: 1,
+        image: project.image,
+        color: project.color,
+        template: project.template,
+        views: project.views,
+        assets: project.assets,
+        owner: project.owner,
+        viewers: project.viewers,
+        editors: project.editors,
+        geometries: project.geometries,
+    };
+
+    let mut tx = pool.begin().await?;
+    project.insert(&mut tx, &claims.email).await?;
+    AuditEntry::record(
+        &mut tx,
+        &claims.email,
+        AuditAction::ProjectCreated,
+        AuditTarget::Project(project.id),
+        project.audit_summary(),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(project.id))
+}
+
+#[axum_macros::debug_handler]
+pub async fn get_project(access: ProjectAccess<Viewer>) -> Result<impl IntoResponse> {
+    let project = access.project;
+    Ok(([(ETAG, etag(project.revision))], Json(project)))
+}
+
+#[axum_macros::debug_handler]
+pub async fn update_project(
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    Extension(limits): Extension<Arc<AssetLimits>>,
+    access: ProjectAccess<Editor>,
+    if_match: IfMatch,
+    ValidJson(mut project): ValidJson<Project>,
+) -> Result<impl IntoResponse> {
+    let saved_project = access.project;
+    let revision = if_match.check(&saved_project)?;
+
+    // Membership may only be managed by the owner.
+    if !saved_project.has_same_members(&project) && access.role != ProjectRole::Owner {
+        return Err(Error::Forbidden);
+    }
+    if saved_project.owner.email.to_lowercase() != project.owner.email.to_lowercase() {
+        return Err(Error::Api(
+            StatusCode::BAD_REQUEST,
+            "Project owner can't be changed.",
+        ));
+    }
+
+    // Assets removed from the project are kept in the bucket,
+    // as they may still be referenced by the project's revisions.
+    ensure_within_quota(&client, &limits, &project.assets).await?;
+    save_assets(&client, &limits, &mut project.assets).await?;
+
+    project.id = saved_project.id;
+    project.created = saved_project.created;
+    project.modified = Some(Utc::now());
+
+    let mut tx = pool.begin().await?;
+    project
+        .update(&mut tx, revision, &access.claims.email)
+        .await?;
+    if !saved_project.has_same_members(&project) {
+        AuditEntry::record(
+            &mut tx,
+            &access.claims.email,
+            AuditAction::MembersChanged,
+            AuditTarget::Project(project.id),
+            project.member_changes(&saved_project),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok((StatusCode::NO_CONTENT, [(ETAG, etag(project.revision))]))
+}
+
+/// Moves a project to the trash, from which it is purged after the retention period.
+#[axum_macros::debug_handler]
+pub async fn delete_project(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Owner>,
+) -> Result<StatusCode> {
+    let project = access.project;
+    let mut tx = pool.begin().await?;
+    Project::trash(&mut tx, project.id).await?;
+    AuditEntry::record(
+        &mut tx,
+        &access.claims.email,
+        AuditAction::ProjectDeleted,
+        AuditTarget::Project(project.id),
+        project.audit_summary(),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum_macros::debug_handler]
+pub async fn update_project_geometries(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Editor>,
+    if_match: IfMatch,
+    ValidJson(geometries): ValidJson<Vec<Geometry>>,
+) -> Result<impl IntoResponse> {
+    let mut project = access.project;
+    let revision = if_match.check(&project)?;
+    project.geometries = geometries;
+    project.modified = Some(Utc::now());
+
+    let mut tx = pool.begin().await?;
+    project
+        .update(&mut tx, revision, &access.claims.email)
+        .await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::NO_CONTENT, [(ETAG, etag(project.revision))]))
+}
+
+/// Lists the projects of the current user, optionally filtered, sorted and paginated.
+/// The cursor of the next page is returned in the [NEXT_CURSOR] header.
+#[axum_macros::debug_handler]
+pub async fn list_projects(
+    Extension(pool): Extension<PgPool>,
+    claims: Claims,
+    Query(query): Query<ProjectQuery>,
+) -> Result<impl IntoResponse> {
+    let mut conn = pool.acquire().await?;
+    let page = Project::search(&mut conn, Some(&claims.email), &query).await?;
+    let projects = Project::fetch_all(&mut conn, &page.ids).await?;
+    Ok((page.headers()?, Json(projects)))
+}
+
+#[axum_macros::debug_handler]
+pub async fn duplicate_project(
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    claims: Claims,
+    ValidJson(project): ValidJson<CreateProject>,
+) -> Result<Json<Uuid>> {
+    // Sanity check
+    if project.owner.email.to_lowercase() != claims.email.to_lowercase() {
+        return Err(Error::Api(
+            StatusCode::BAD_REQUEST,
+            "Project owner does not match token claims.",
+        ));
+    }
+
+    // Create project
+    let mut duplicate = Project {
+        id: Uuid::new_v4(),
+        title: project.title,
+        description: project.description,
+        created: Utc::now(),
+        modified: None,
+        revision: 1,
+        image: project.image,
+        color: project.color,
+        template: project.template,
+        views: project.views,
+        assets: Vec::new(),
+        owner: project.owner,
+        viewers: Vec::new(),
+        editors: Vec::new(),
+        geometries: project.geometries,
+    };
+
+    duplicate.assets = copy_assets(&client, &project.assets).await?;
+
+    let mut tx = pool.begin().await?;
+    duplicate.insert(&mut tx, &claims.email).await?;
+    AuditEntry::record(
+        &mut tx,
+        &claims.email,
+        AuditAction::ProjectCreated,
+        AuditTarget::Project(duplicate.id),
+        duplicate.audit_summary(),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(duplicate.id))
+}
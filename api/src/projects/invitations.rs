@@ -0,0 +1,297 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Json, Path};
+use axum::http::StatusCode;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::audit::{AuditAction, AuditEntry, AuditTarget};
+use crate::auth::Claims;
+use crate::projects::{Member, Owner, Project, ProjectAccess, ProjectRole};
+use crate::{Error, Result};
+
+/// Configuration for project invitation links.
+#[derive(clap::Parser, Debug, Clone)]
+pub struct InvitationConfig {
+    /// The secret with which invitation tokens are signed.
+    #[clap(long, env, hide_env_values = true)]
+    pub project_invitation_secret: String,
+
+    /// The longest time for which an invitation can be valid, in seconds.
+    #[clap(long, env, default_value = "2592000")]
+    pub project_invitation_max_ttl: i64,
+}
+
+/// A link through which users can join a project with a specific role.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectInvitation {
+    pub id: Uuid,
+    pub role: ProjectRole,
+    pub created_by: String,
+    pub created: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+
+    /// Whether the invitation is revoked once it has been redeemed.
+    pub single_use: bool,
+
+    /// The number of times the invitation has been redeemed.
+    pub redemptions: i32,
+
+    /// The signed token that has to be presented to redeem the invitation.
+    pub token: String,
+}
+
+/// The claims of an invitation token.
+#[derive(Serialize, Deserialize)]
+struct InvitationClaims {
+    /// The id of the invitation.
+    sub: Uuid,
+    exp: i64,
+}
+
+impl InvitationConfig {
+    fn sign(&self, invitation: &mut ProjectInvitation) -> Result<()> {
+        let claims = InvitationClaims {
+            sub: invitation.id,
+            exp: invitation.expires.timestamp(),
+        };
+        let key = EncodingKey::from_secret(self.project_invitation_secret.as_bytes());
+        invitation.token = jsonwebtoken::encode(&Header::default(), &claims, &key)
+            .map_err(|_| Error::Jwt("Failed to sign invitation token"))?;
+        Ok(())
+    }
+
+    /// Verifies the signature and expiry of a token, and returns the id of its invitation.
+    fn verify(&self, token: &str) -> Result<Uuid> {
+        let key = DecodingKey::from_secret(self.project_invitation_secret.as_bytes());
+        let mut validation = Validation::default();
+        validation.leeway = 0;
+        let data = jsonwebtoken::decode::<InvitationClaims>(token, &key, &validation)
+            .map_err(|_| Error::Api(StatusCode::GONE, "Invitation is invalid or has expired."))?;
+        Ok(data.claims.sub)
+    }
+}
+
+impl ProjectInvitation {
+    /// Loads the invitations of a project that have not yet expired.
+    async fn fetch_all(conn: &mut PgConnection, project_id: Uuid) -> Result<Vec<Self>> {
+        let invitations = sqlx::query_as!(
+            ProjectInvitation,
+            r#"
+            SELECT id, role AS "role: ProjectRole", created_by, created, expires, single_use,
+                   redemptions, '' AS "token!"
+            FROM project_invitations
+            WHERE project_id = $1 AND expires > now()
+            ORDER BY created DESC
+            "#,
+            project_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(invitations)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateInvitation {
+    pub role: ProjectRole,
+
+    /// The time for which the invitation is valid, in seconds.
+    /// Defaults to the longest allowed time.
+    pub expires_in: Option<i64>,
+
+    #[serde(default)]
+    pub single_use: bool,
+}
+
+#[derive(Deserialize)]
+pub struct RedeemInvitation {
+    pub token: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedeemedInvitation {
+    pub project_id: Uuid,
+    pub role: ProjectRole,
+}
+
+#[derive(Deserialize)]
+pub struct InvitationPath {
+    invitation: Uuid,
+}
+
+#[axum_macros::debug_handler]
+pub async fn create_invitation(
+    Extension(pool): Extension<PgPool>,
+    Extension(config): Extension<Arc<InvitationConfig>>,
+    access: ProjectAccess<Owner>,
+    Json(request): Json<CreateInvitation>,
+) -> Result<(StatusCode, Json<ProjectInvitation>)> {
+    if request.role == ProjectRole::Owner {
+        return Err(Error::Api(
+            StatusCode::BAD_REQUEST,
+            "Invitations can't grant ownership.",
+        ));
+    }
+    let expires_in = request
+        .expires_in
+        .unwrap_or(config.project_invitation_max_ttl);
+    if expires_in <= 0 || expires_in > config.project_invitation_max_ttl {
+        return Err(Error::Api(
+            StatusCode::BAD_REQUEST,
+            "Invitation expiry is out of range.",
+        ));
+    }
+
+    let created = Utc::now();
+    let mut invitation = ProjectInvitation {
+        id: Uuid::new_v4(),
+        role: request.role,
+        created_by: access.claims.email.to_lowercase(),
+        created,
+        expires: created + Duration::seconds(expires_in),
+        single_use: request.single_use,
+        redemptions: 0,
+        token: String::new(),
+    };
+    sqlx::query!(
+        r#"
+        INSERT INTO project_invitations
+            (id, project_id, role, created_by, created, expires, single_use)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        invitation.id,
+        access.project.id,
+        invitation.role as ProjectRole,
+        invitation.created_by,
+        invitation.created,
+        invitation.expires,
+        invitation.single_use,
+    )
+    .execute(&pool)
+    .await?;
+
+    config.sign(&mut invitation)?;
+    Ok((StatusCode::CREATED, Json(invitation)))
+}
+
+#[axum_macros::debug_handler]
+pub async fn list_invitations(
+    Extension(pool): Extension<PgPool>,
+    Extension(config): Extension<Arc<InvitationConfig>>,
+    access: ProjectAccess<Owner>,
+) -> Result<Json<Vec<ProjectInvitation>>> {
+    let mut conn = pool.acquire().await?;
+    let mut invitations = ProjectInvitation::fetch_all(&mut conn, access.project.id).await?;
+    for invitation in &mut invitations {
+        config.sign(invitation)?;
+    }
+    Ok(Json(invitations))
+}
+
+#[axum_macros::debug_handler]
+pub async fn revoke_invitation(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Owner>,
+    Path(InvitationPath { invitation }): Path<InvitationPath>,
+) -> Result<StatusCode> {
+    let result = sqlx::query!(
+        "DELETE FROM project_invitations WHERE id = $1 AND project_id = $2",
+        invitation,
+        access.project.id
+    )
+    .execute(&pool)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(Error::NotFound);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Adds the current user to the project of an invitation.
+///
+/// Members that already have the invitation's role, or a higher one, are left unchanged.
+#[axum_macros::debug_handler]
+pub async fn redeem_invitation(
+    Extension(pool): Extension<PgPool>,
+    Extension(config): Extension<Arc<InvitationConfig>>,
+    claims: Claims,
+    Json(request): Json<RedeemInvitation>,
+) -> Result<Json<RedeemedInvitation>> {
+    let id = config.verify(&request.token)?;
+
+    let mut tx = pool.begin().await?;
+    let invitation = sqlx::query!(
+        r#"
+        SELECT project_id, role AS "role: ProjectRole", single_use
+        FROM project_invitations
+        WHERE id = $1 AND expires > now()
+        FOR UPDATE
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(Error::Api(
+        StatusCode::GONE,
+        "Invitation is invalid or has expired.",
+    ))?;
+
+    let mut project = Project::fetch(&mut tx, invitation.project_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let current_role = project.role_of(&claims.email);
+    if current_role >= Some(invitation.role) {
+        return Ok(Json(RedeemedInvitation {
+            project_id: project.id,
+            role: current_role.unwrap_or(invitation.role),
+        }));
+    }
+
+    let before = project.clone();
+    project.set_role(
+        Member {
+            email: claims.email.to_lowercase(),
+            name: claims.given_name.clone(),
+            surname: claims.family_name.clone(),
+        },
+        Some(invitation.role),
+    );
+    project.modified = Some(Utc::now());
+    project
+        .update(&mut tx, project.revision, &claims.email)
+        .await?;
+    AuditEntry::record(
+        &mut tx,
+        &claims.email,
+        AuditAction::MembersChanged,
+        AuditTarget::Project(project.id),
+        project.member_changes(&before),
+    )
+    .await?;
+
+    if invitation.single_use {
+        sqlx::query!("DELETE FROM project_invitations WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+    } else {
+        sqlx::query!(
+            "UPDATE project_invitations SET redemptions = redemptions + 1 WHERE id = $1",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(Json(RedeemedInvitation {
+        project_id: project.id,
+        role: invitation.role,
+    }))
+}
@@ -0,0 +1,188 @@
+use serde_json::Number;
+
+use crate::projects::geodesy::Lv95;
+use crate::projects::{Cartesian3, Geometry, GeometryType, Project};
+
+impl Geometry {
+    /// Recomputes the measurements of this geometry from its positions.
+    ///
+    /// Measurements are done in LV95/LN02, in kilometers and rounded to meters.
+    /// As in the viewer, the rings of polygons and rectangles are closed,
+    /// and their sides include the closing segment.
+    /// Values sent by clients are replaced, so that stored measurements can be relied upon.
+    pub fn measure(&mut self) {
+        let positions: Vec<Lv95> = self.positions.iter().map(Cartesian3::to_lv95).collect();
+        let is_area = matches!(self.typ, GeometryType::Polygon | GeometryType::Rectangle)
+            && positions.len() > 2;
+
+        let mut segments: Vec<f64> = positions
+            .windows(2)
+            .map(|it| distance(&it[0], &it[1]))
+            .collect();
+        if is_area && positions.first() != positions.last() {
+            segments.push(distance(&positions[positions.len() - 1], &positions[0]));
+        }
+        let area = is_area.then(|| area(&positions));
+
+        self.number_of_segments = Some(positions.len().saturating_sub(1).into());
+        self.sides_length = (!segments.is_empty()).then(|| {
+            segments
+                .iter()
+                .filter_map(|it| Number::from_f64(round(*it)))
+                .collect()
+        });
+        self.perimeter = (!segments.is_empty()).then(|| format_km(segments.iter().sum()));
+        self.area = area.map(format_km);
+        self.volume = match (area, &self.volume_height_limits) {
+            (Some(area), Some(limits)) => limits
+                .height
+                .as_f64()
+                .map(|height| format_km(area * height / 1000.0)),
+            _ => None,
+        };
+    }
+}
+
+impl Project {
+    /// Recomputes the measurements of all geometries of this project.
+    pub fn measure_geometries(&mut self) {
+        self.geometries.iter_mut().for_each(Geometry::measure);
+    }
+}
+
+/// The distance between two positions, in kilometers.
+fn distance(a: &Lv95, b: &Lv95) -> f64 {
+    let horizontal = (a.east - b.east).hypot(a.north - b.north);
+    horizontal.hypot(a.height - b.height) / 1000.0
+}
+
+/// The horizontal area enclosed by a ring of positions, in square kilometers.
+fn area(ring: &[Lv95]) -> f64 {
+    // Coordinates are taken relative to the first position to not lose precision.
+    let origin = ring[0];
+    let twice_area: f64 = ring
+        .iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| {
+            (a.east - origin.east) * (b.north - origin.north)
+                - (b.east - origin.east) * (a.north - origin.north)
+        })
+        .sum();
+    twice_area.abs() / 2.0 / 1_000_000.0
+}
+
+fn round(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+fn format_km(value: f64) -> String {
+    format!("{value:.3}")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::projects::GeometryVolumeHeightLimits;
+    use crate::projects::geodesy::lv95_to_wgs84;
+
+    /// Creates a geometry from LV95 positions, relative to Bern.
+    fn geometry(typ: &str, positions: &[(f64, f64)]) -> Geometry {
+        let positions: Vec<Cartesian3> = positions
+            .iter()
+            .map(|(east, north)| {
+                Cartesian3::from_wgs84(lv95_to_wgs84(Lv95 {
+                    east: 2_600_000.0 + east,
+                    north: 1_200_000.0 + north,
+                    height: 500.0,
+                }))
+                .unwrap()
+            })
+            .collect();
+        serde_json::from_value(json!({ "type": typ, "positions": positions })).unwrap()
+    }
+
+    fn assert_km(actual: Option<&str>, expected: f64) {
+        let actual: f64 = actual.expect("missing measurement").parse().unwrap();
+        assert!(
+            (actual - expected).abs() <= 0.002,
+            "expected {expected} km, got {actual} km"
+        );
+    }
+
+    fn sides(geometry: &Geometry) -> Vec<f64> {
+        geometry
+            .sides_length
+            .iter()
+            .flatten()
+            .map(|it| it.as_f64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn points_have_no_length() {
+        let mut point = geometry("point", &[(0.0, 0.0)]);
+        point.measure();
+        assert_eq!(point.number_of_segments, Some(0.into()));
+        assert_eq!(point.perimeter, None);
+        assert_eq!(point.sides_length, None);
+        assert_eq!(point.area, None);
+    }
+
+    #[test]
+    fn lines_are_not_closed() {
+        let mut line = geometry("line", &[(0.0, 0.0), (1000.0, 0.0), (1000.0, 2000.0)]);
+        line.measure();
+        assert_eq!(line.number_of_segments, Some(2.into()));
+        assert_km(line.perimeter.as_deref(), 3.0);
+        let sides = sides(&line);
+        assert_eq!(sides.len(), 2);
+        assert!((sides[0] - 1.0).abs() <= 0.002 && (sides[1] - 2.0).abs() <= 0.002);
+        assert_eq!(line.area, None);
+    }
+
+    #[test]
+    fn rectangles_are_closed() {
+        let mut rectangle = geometry(
+            "rectangle",
+            &[(0.0, 0.0), (1000.0, 0.0), (1000.0, 2000.0), (0.0, 2000.0)],
+        );
+        rectangle.measure();
+        assert_eq!(rectangle.number_of_segments, Some(3.into()));
+        assert_km(rectangle.perimeter.as_deref(), 6.0);
+        assert_eq!(sides(&rectangle).len(), 4);
+        assert_km(rectangle.area.as_deref(), 2.0);
+        assert_eq!(rectangle.volume, None);
+    }
+
+    #[test]
+    fn closed_polygons_are_not_closed_twice() {
+        let triangle = [(0.0, 0.0), (3000.0, 0.0), (3000.0, 4000.0)];
+        let mut open = geometry("polygon", &triangle);
+        open.measure();
+        let mut closed = geometry("polygon", &[&triangle[..], &triangle[..1]].concat());
+        closed.measure();
+
+        assert_km(open.perimeter.as_deref(), 12.0);
+        assert_eq!(open.perimeter, closed.perimeter);
+        assert_eq!(sides(&open).len(), 3);
+        assert_eq!(sides(&closed).len(), 3);
+        assert_km(open.area.as_deref(), 6.0);
+        assert_eq!(open.area, closed.area);
+    }
+
+    #[test]
+    fn volumes_use_the_height_limits() {
+        let mut rectangle = geometry(
+            "rectangle",
+            &[(0.0, 0.0), (1000.0, 0.0), (1000.0, 2000.0), (0.0, 2000.0)],
+        );
+        rectangle.volume_height_limits = Some(GeometryVolumeHeightLimits {
+            lower_limit: (-100).into(),
+            height: 500.into(),
+        });
+        rectangle.measure();
+        assert_km(rectangle.volume.as_deref(), 1.0);
+    }
+}
@@ -0,0 +1,248 @@
+use axum::extract::{Extension, Json, Path};
+use axum::http::StatusCode;
+use axum::http::header::ETAG;
+use axum::response::IntoResponse;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sqlx::PgPool;
+
+use crate::audit::{AuditAction, AuditEntry, AuditTarget};
+use crate::projects::{
+    IfMatch, Member, Owner, Project, ProjectAccess, ProjectRole, RequiredRole, Viewer, etag,
+};
+use crate::{Error, Result};
+
+/// A member of a project, along with their role.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectMember {
+    #[serde(flatten)]
+    pub member: Member,
+    pub role: ProjectRole,
+}
+
+impl Project {
+    /// Lists all members of this project, starting with the owner.
+    pub fn members(&self) -> Vec<ProjectMember> {
+        let with_role = |role| {
+            move |member: &Member| ProjectMember {
+                member: member.clone(),
+                role,
+            }
+        };
+        std::iter::once(with_role(ProjectRole::Owner)(&self.owner))
+            .chain(self.editors.iter().map(with_role(ProjectRole::Editor)))
+            .chain(self.viewers.iter().map(with_role(ProjectRole::Viewer)))
+            .collect()
+    }
+
+    /// The project's title and members,
+    /// as recorded in the audit log when the project is created or deleted.
+    pub fn audit_summary(&self) -> Value {
+        json!({ "title": self.title, "members": self.members() })
+    }
+
+    /// Describes how the members of this project differ from the ones of `before`,
+    /// as recorded in the audit log.
+    pub fn member_changes(&self, before: &Project) -> Value {
+        let (before, after) = (before.members(), self.members());
+        let find = |members: &[ProjectMember], email: &str| {
+            members
+                .iter()
+                .find(|it| it.member.email.to_lowercase() == email.to_lowercase())
+                .cloned()
+        };
+        let added: Vec<&ProjectMember> = after
+            .iter()
+            .filter(|it| find(&before, &it.member.email).is_none())
+            .collect();
+        let removed: Vec<&ProjectMember> = before
+            .iter()
+            .filter(|it| find(&after, &it.member.email).is_none())
+            .collect();
+        let changed: Vec<Value> = after
+            .iter()
+            .filter_map(|it| {
+                let previous = find(&before, &it.member.email)?;
+                (previous.role != it.role).then(|| {
+                    json!({
+                        "email": it.member.email,
+                        "before": previous.role,
+                        "after": it.role,
+                    })
+                })
+            })
+            .collect();
+        json!({ "added": added, "removed": removed, "changed": changed })
+    }
+
+    /// Finds the member with the given email.
+    pub(super) fn member(&self, email: &str) -> Option<ProjectMember> {
+        let email = email.to_lowercase();
+        self.members()
+            .into_iter()
+            .find(|it| it.member.email.to_lowercase() == email)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MemberPath {
+    email: String,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateMember {
+    pub role: ProjectRole,
+    /// Defaults to the member's current name, if they are already a member.
+    pub name: Option<String>,
+    /// Defaults to the member's current surname, if they are already a member.
+    pub surname: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TransferOwnership {
+    /// The email of the member that becomes the new owner.
+    pub email: String,
+}
+
+#[axum_macros::debug_handler]
+pub async fn list_members(access: ProjectAccess<Viewer>) -> Json<Vec<ProjectMember>> {
+    Json(access.project.members())
+}
+
+/// Adds a member to a project, or changes the role of an existing one.
+#[axum_macros::debug_handler]
+pub async fn put_member(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Owner>,
+    if_match: IfMatch,
+    Path(MemberPath { email }): Path<MemberPath>,
+    Json(request): Json<UpdateMember>,
+) -> Result<impl IntoResponse> {
+    let mut project = access.project.clone();
+    let revision = if_match.check(&project)?;
+    if request.role == ProjectRole::Owner {
+        return Err(Error::Api(
+            StatusCode::BAD_REQUEST,
+            "Ownership can only be transferred to an existing member.",
+        ));
+    }
+
+    let current = project.member(&email);
+    if current.as_ref().map(|it| it.role) == Some(ProjectRole::Owner) {
+        return Err(Error::Api(
+            StatusCode::BAD_REQUEST,
+            "The owner's role can't be changed.",
+        ));
+    }
+    let current = current.map(|it| it.member);
+    let member = Member {
+        email: email.to_lowercase(),
+        name: request
+            .name
+            .or_else(|| current.as_ref().map(|it| it.name.clone()))
+            .unwrap_or_default(),
+        surname: request
+            .surname
+            .or_else(|| current.as_ref().map(|it| it.surname.clone()))
+            .unwrap_or_default(),
+    };
+    project.set_role(member.clone(), Some(request.role));
+    save(&pool, &access, &mut project, revision).await?;
+
+    let member = ProjectMember {
+        member,
+        role: request.role,
+    };
+    Ok(([(ETAG, etag(project.revision))], Json(member)))
+}
+
+/// Removes a member from a project.
+///
+/// The owner may remove any other member, while all other members may only remove themselves.
+#[axum_macros::debug_handler]
+pub async fn remove_member(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Viewer>,
+    if_match: IfMatch,
+    Path(MemberPath { email }): Path<MemberPath>,
+) -> Result<impl IntoResponse> {
+    let mut project = access.project.clone();
+    let revision = if_match.check(&project)?;
+    let is_self = email.to_lowercase() == access.claims.email.to_lowercase();
+    if access.role != ProjectRole::Owner && !is_self {
+        return Err(Error::Forbidden);
+    }
+
+    let member = project.member(&email).ok_or(Error::NotFound)?;
+    if member.role == ProjectRole::Owner {
+        return Err(Error::Api(
+            StatusCode::BAD_REQUEST,
+            "The owner can't be removed from the project.",
+        ));
+    }
+    project.set_role(member.member, None);
+    save(&pool, &access, &mut project, revision).await?;
+
+    Ok((StatusCode::NO_CONTENT, [(ETAG, etag(project.revision))]))
+}
+
+/// Hands the ownership of a project to one of its members.
+/// The previous owner stays in the project as an editor.
+#[axum_macros::debug_handler]
+pub async fn transfer_ownership(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Owner>,
+    if_match: IfMatch,
+    Json(request): Json<TransferOwnership>,
+) -> Result<impl IntoResponse> {
+    let mut project = access.project.clone();
+    let revision = if_match.check(&project)?;
+
+    let member = project
+        .member(&request.email)
+        .ok_or(Error::Api(
+            StatusCode::BAD_REQUEST,
+            "Ownership can only be transferred to an existing member.",
+        ))?
+        .member;
+    if member.email.to_lowercase() == project.owner.email.to_lowercase() {
+        return Ok((StatusCode::NO_CONTENT, [(ETAG, etag(project.revision))]));
+    }
+
+    project.set_role(member.clone(), None);
+    let previous_owner = std::mem::replace(&mut project.owner, member);
+    project.set_role(previous_owner, Some(ProjectRole::Editor));
+    save(&pool, &access, &mut project, revision).await?;
+
+    Ok((StatusCode::NO_CONTENT, [(ETAG, etag(project.revision))]))
+}
+
+/// Stores a membership change as a new revision of the project, and records it in the audit log.
+async fn save<R: RequiredRole>(
+    pool: &PgPool,
+    access: &ProjectAccess<R>,
+    project: &mut Project,
+    revision: i32,
+) -> Result<()> {
+    let author = &access.claims.email;
+    project.modified = Some(Utc::now());
+    let mut tx = pool.begin().await?;
+    project.update(&mut tx, revision, author).await?;
+    AuditEntry::record(
+        &mut tx,
+        author,
+        AuditAction::MembersChanged,
+        AuditTarget::Project(project.id),
+        project.member_changes(&access.project),
+    )
+    .await?;
+    tx.commit().await?;
+    tracing::info!(
+        "Members of project {} changed by {author} in revision {}.",
+        project.id,
+        project.revision
+    );
+    Ok(())
+}
@@ -0,0 +1,308 @@
+use axum::{
+    Router,
+    extract::DefaultBodyLimit,
+    routing::{delete, get, post, put},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Number;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::projects::assets::AssetFormat;
+
+mod access;
+pub use access::*;
+
+pub mod admin;
+mod archive;
+
+pub mod assets;
+mod collaboration;
+pub use collaboration::Collaboration;
+mod conversion;
+pub use conversion::ConversionError;
+
+mod etag;
+pub use etag::*;
+
+mod gc;
+pub use gc::{AssetGc, AssetGcReport};
+
+pub mod geodesy;
+mod geojson;
+mod geometries;
+
+mod measure;
+
+mod invitations;
+pub use invitations::InvitationConfig;
+
+mod members;
+
+pub mod search;
+
+mod handlers;
+
+mod revisions;
+pub use revisions::*;
+
+mod store;
+
+pub mod templates;
+
+mod trash;
+pub use trash::ProjectTrash;
+
+mod validation;
+
+/// Creates the router serving all project endpoints.
+/// It is expected to be nested under `/api/projects`.
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(handlers::list_projects).post(handlers::create_project),
+        )
+        .route("/duplicate", post(handlers::duplicate_project))
+        .route("/trash", get(trash::list_trash))
+        .route(
+            "/upload_asset",
+            // Uploads are streamed and limited by `AssetLimits` instead.
+            post(assets::upload_asset).layer(DefaultBodyLimit::disable()),
+        )
+        .route("/upload_url", post(assets::create_upload_url))
+        .route(
+            "/import",
+            // Archives are limited by `AssetLimits` instead.
+            post(archive::import_project).layer(DefaultBodyLimit::disable()),
+        )
+        .route(
+            "/:id",
+            get(handlers::get_project)
+                .put(handlers::update_project)
+                .delete(handlers::delete_project),
+        )
+        .route("/:id/restore", post(trash::restore_project))
+        .route("/:id/instances", post(templates::create_from_template))
+        .route("/:id/assets/:key/url", get(assets::get_asset_url))
+        .route("/:id/export", get(archive::export_project))
+        .route("/:id/events", get(collaboration::project_events))
+        .route(
+            "/:id/invitations",
+            get(invitations::list_invitations).post(invitations::create_invitation),
+        )
+        .route(
+            "/:id/invitations/:invitation",
+            delete(invitations::revoke_invitation),
+        )
+        .route("/invitations/redeem", post(invitations::redeem_invitation))
+        .route("/:id/members", get(members::list_members))
+        .route(
+            "/:id/members/:email",
+            put(members::put_member).delete(members::remove_member),
+        )
+        .route("/:id/owner", put(members::transfer_ownership))
+        .route(
+            "/:id/geometries",
+            put(handlers::update_project_geometries).post(geometries::create_geometry),
+        )
+        .route(
+            "/:id/geometries/geojson",
+            get(geojson::get_project_geojson).put(geojson::update_project_geojson),
+        )
+        .route(
+            "/:id/geometries/:geometry",
+            get(geometries::get_geometry)
+                .put(geometries::update_geometry)
+                .delete(geometries::delete_geometry),
+        )
+        .route(
+            "/:id/geometries/:geometry/position",
+            put(geometries::move_geometry),
+        )
+        .route("/:id/revisions", get(revisions::list_revisions))
+        .route("/:id/revisions/diff", get(revisions::diff_revisions))
+        .route("/:id/revisions/:revision", get(revisions::get_revision))
+        .route(
+            "/:id/revisions/:revision/restore",
+            post(revisions::restore_revision),
+        )
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, FromRow)]
+pub struct CreateProject {
+    pub owner: Member,
+    #[serde(default)]
+    pub viewers: Vec<Member>,
+    #[serde(default)]
+    pub editors: Vec<Member>,
+    pub title: String,
+    pub description: Option<String>,
+    pub image: Option<String>,
+    pub color: String,
+    /// Whether the project serves as a template for new projects.
+    #[serde(default)]
+    pub template: bool,
+    #[serde(default)]
+    pub views: Vec<View>,
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+    #[serde(default)]
+    pub geometries: Vec<Geometry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, FromRow)]
+pub struct Project {
+    pub id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub created: DateTime<Utc>,
+    pub modified: Option<DateTime<Utc>>,
+    /// A counter that is incremented each time the project is saved.
+    /// Used to detect concurrent modifications, see [IfMatch].
+    #[serde(default)]
+    pub revision: i32,
+    pub image: Option<String>,
+    pub color: String,
+    /// Whether the project serves as a template for new projects,
+    /// see [templates::create_from_template].
+    #[serde(default)]
+    pub template: bool,
+    #[serde(default)]
+    pub views: Vec<View>,
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+    pub owner: Member,
+    #[serde(default)]
+    pub viewers: Vec<Member>,
+    #[serde(default)]
+    pub editors: Vec<Member>,
+    #[serde(default)]
+    pub geometries: Vec<Geometry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, FromRow)]
+pub struct View {
+    pub id: String,
+    pub title: String,
+    pub permalink: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Asset {
+    pub name: String,
+    pub key: String,
+    pub clamp_to_ground: Option<bool>,
+    /// The name of the file from which the asset has been created.
+    #[serde(default)]
+    pub original_name: Option<String>,
+    /// The format in which the asset has been uploaded.
+    /// The stored asset itself is always converted to KML.
+    #[serde(default)]
+    pub format: Option<AssetFormat>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, FromRow)]
+pub struct Member {
+    pub email: String,
+    pub name: String,
+    pub surname: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Geometry {
+    #[serde(rename = "type")]
+    typ: GeometryType,
+    positions: Vec<Cartesian3>,
+    id: Option<String>,
+    name: Option<String>,
+    show: Option<bool>,
+    area: Option<String>,
+    perimeter: Option<String>,
+    sides_length: Option<Vec<Number>>,
+    number_of_segments: Option<Number>,
+    description: Option<String>,
+    image: Option<String>,
+    website: Option<String>,
+    point_symbol: Option<String>,
+    color: Option<CesiumColor>,
+    clamp_point: Option<bool>,
+    show_slicing_box: Option<bool>,
+    volume_showed: Option<bool>,
+    volume_height_limits: Option<GeometryVolumeHeightLimits>,
+    /// The volume within the geometry's height limits, computed by the API.
+    volume: Option<String>,
+    swissforages_id: Option<String>,
+    depth: Option<f64>,
+    diameter: Option<f64>,
+    editable: Option<bool>,
+    copyable: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GeometryType {
+    Point,
+    Line,
+    Polygon,
+    Rectangle,
+    /// A type that is not supported, kept as it is so that stored geometries can still be read.
+    /// Geometries of such a type are rejected once they are written again.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, FromRow)]
+struct Cartesian3 {
+    x: Number,
+    y: Number,
+    z: Number,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, FromRow)]
+struct CesiumColor {
+    red: Number,
+    green: Number,
+    blue: Number,
+    alpha: Number,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, FromRow)]
+#[serde(rename_all = "camelCase")]
+struct GeometryVolumeHeightLimits {
+    lower_limit: Number,
+    height: Number,
+}
+
+impl Project {
+    /// Whether the members of `other` are the same as the ones of this project.
+    pub fn has_same_members(&self, other: &Project) -> bool {
+        fn emails(members: &[Member]) -> Vec<String> {
+            let mut emails: Vec<String> =
+                members.iter().map(|it| it.email.to_lowercase()).collect();
+            emails.sort();
+            emails
+        }
+        self.owner.email.to_lowercase() == other.owner.email.to_lowercase()
+            && emails(&self.viewers) == emails(&other.viewers)
+            && emails(&self.editors) == emails(&other.editors)
+    }
+
+    /// Gives `member` the role of an editor or viewer, replacing any such role they had before.
+    /// Passing `None` removes them from the project's editors and viewers.
+    ///
+    /// The owner is not affected by this.
+    pub fn set_role(&mut self, member: Member, role: Option<ProjectRole>) {
+        let email = member.email.to_lowercase();
+        self.editors.retain(|it| it.email.to_lowercase() != email);
+        self.viewers.retain(|it| it.email.to_lowercase() != email);
+        match role {
+            Some(ProjectRole::Editor) => self.editors.push(member),
+            Some(ProjectRole::Viewer) => self.viewers.push(member),
+            Some(ProjectRole::Owner) | None => {}
+        }
+    }
+}
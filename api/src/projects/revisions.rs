@@ -0,0 +1,269 @@
+use std::collections::BTreeSet;
+
+use axum::extract::{Extension, Json, Path, Query};
+use axum::http::header::ETAG;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::projects::collaboration::RevisionNotification;
+use crate::projects::{Asset, Editor, IfMatch, Project, ProjectAccess, Viewer, etag};
+use crate::{Error, Result};
+
+/// Fields that change on every save, and are therefore not considered to be modifications.
+const IGNORED_FIELDS: &[&str] = &["id", "created", "modified", "revision"];
+
+/// A saved state of a project.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectRevision {
+    /// The project's revision, see [Project::revision].
+    pub revision: i32,
+
+    /// The email of the user that saved the revision.
+    pub author: String,
+
+    /// The time at which the revision was saved.
+    pub created: DateTime<Utc>,
+
+    /// The names of the project fields that were modified by this revision.
+    /// `None` for the initial revision, or if the preceding revision has not been recorded.
+    pub changes: Option<Vec<String>>,
+}
+
+/// The differences between two revisions of a project.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectRevisionDiff {
+    pub from: i32,
+    pub to: i32,
+    pub changes: Vec<ProjectFieldChange>,
+}
+
+/// A project field that differs between two revisions.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectFieldChange {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+impl ProjectRevision {
+    /// Records the current state of `project` as a new revision.
+    pub(super) async fn record(
+        conn: &mut PgConnection,
+        project: &Project,
+        author: &str,
+    ) -> Result<()> {
+        let previous = Self::fetch_snapshot(conn, project.id, project.revision - 1).await?;
+        let changes = previous.map(|previous| {
+            diff(&previous, project)
+                .into_iter()
+                .map(|change| change.field)
+                .collect::<Vec<_>>()
+        });
+
+        sqlx::query!(
+            r#"
+            INSERT INTO project_revisions (project_id, revision, author, created, changes, snapshot)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            project.id,
+            project.revision,
+            author.to_lowercase(),
+            project.modified.unwrap_or(project.created),
+            changes.as_deref(),
+            sqlx::types::Json(project) as _,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        RevisionNotification {
+            project_id: project.id,
+            revision: project.revision,
+            author: author.to_lowercase(),
+            changes,
+        }
+        .send(conn)
+        .await
+    }
+
+    /// Loads all recorded revisions of a project, most recent first.
+    pub async fn fetch_all(conn: &mut PgConnection, project_id: Uuid) -> Result<Vec<Self>> {
+        let revisions = sqlx::query_as!(
+            ProjectRevision,
+            r#"
+            SELECT revision, author, created, changes
+            FROM project_revisions
+            WHERE project_id = $1
+            ORDER BY revision DESC
+            "#,
+            project_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(revisions)
+    }
+
+    /// Loads the state of a project at a specific revision.
+    /// Returns `None` if that revision has not been recorded.
+    pub async fn fetch_snapshot(
+        conn: &mut PgConnection,
+        project_id: Uuid,
+        revision: i32,
+    ) -> Result<Option<Project>> {
+        let snapshot = sqlx::query_scalar!(
+            r#"
+            SELECT snapshot AS "snapshot: sqlx::types::Json<Project>"
+            FROM project_revisions
+            WHERE project_id = $1 AND revision = $2
+            "#,
+            project_id,
+            revision
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+        Ok(snapshot.map(|it| it.0))
+    }
+
+    /// Loads the assets referenced by any recorded revision of a project.
+    pub async fn fetch_assets(conn: &mut PgConnection, project_id: Uuid) -> Result<Vec<Asset>> {
+        let assets = sqlx::query_scalar!(
+            r#"
+            SELECT DISTINCT asset AS "asset!: sqlx::types::Json<Asset>"
+            FROM project_revisions, jsonb_array_elements(snapshot->'assets') AS asset
+            WHERE project_id = $1
+            "#,
+            project_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(assets.into_iter().map(|it| it.0).collect())
+    }
+}
+
+/// Determines the fields that differ between two states of a project.
+fn diff(before: &Project, after: &Project) -> Vec<ProjectFieldChange> {
+    let (Ok(Value::Object(before)), Ok(Value::Object(after))) =
+        (serde_json::to_value(before), serde_json::to_value(after))
+    else {
+        return vec![];
+    };
+    let fields: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    fields
+        .into_iter()
+        .filter(|field| !IGNORED_FIELDS.contains(&field.as_str()))
+        .filter_map(|field| {
+            let old = before.get(field).cloned().unwrap_or(Value::Null);
+            let new = after.get(field).cloned().unwrap_or(Value::Null);
+            (old != new).then(|| ProjectFieldChange {
+                field: field.clone(),
+                before: old,
+                after: new,
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+pub struct RevisionPath {
+    revision: i32,
+}
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    from: i32,
+
+    /// The revision to compare against.
+    /// Defaults to the project's current revision.
+    to: Option<i32>,
+}
+
+#[axum_macros::debug_handler]
+pub async fn list_revisions(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Viewer>,
+) -> Result<Json<Vec<ProjectRevision>>> {
+    let mut conn = pool.acquire().await?;
+    let revisions = ProjectRevision::fetch_all(&mut conn, access.project.id).await?;
+    Ok(Json(revisions))
+}
+
+#[axum_macros::debug_handler]
+pub async fn get_revision(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Viewer>,
+    Path(RevisionPath { revision }): Path<RevisionPath>,
+) -> Result<Json<Project>> {
+    let mut conn = pool.acquire().await?;
+    let snapshot = ProjectRevision::fetch_snapshot(&mut conn, access.project.id, revision)
+        .await?
+        .ok_or(Error::NotFound)?;
+    Ok(Json(snapshot))
+}
+
+#[axum_macros::debug_handler]
+pub async fn diff_revisions(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Viewer>,
+    Query(query): Query<DiffQuery>,
+) -> Result<Json<ProjectRevisionDiff>> {
+    let mut conn = pool.acquire().await?;
+    let id = access.project.id;
+    let before = ProjectRevision::fetch_snapshot(&mut conn, id, query.from)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let after = match query.to {
+        Some(revision) if revision != access.project.revision => {
+            ProjectRevision::fetch_snapshot(&mut conn, id, revision)
+                .await?
+                .ok_or(Error::NotFound)?
+        }
+        _ => access.project,
+    };
+    Ok(Json(ProjectRevisionDiff {
+        from: before.revision,
+        to: after.revision,
+        changes: diff(&before, &after),
+    }))
+}
+
+/// Restores the content of a project to the state of an earlier revision.
+/// The restored state is saved as a new revision.
+///
+/// Only the project's content is restored.
+/// Its membership is left unchanged, as it can only be managed by the owner.
+#[axum_macros::debug_handler]
+pub async fn restore_revision(
+    Extension(pool): Extension<PgPool>,
+    access: ProjectAccess<Editor>,
+    if_match: IfMatch,
+    Path(RevisionPath { revision }): Path<RevisionPath>,
+) -> Result<impl IntoResponse> {
+    let mut project = access.project;
+    let current_revision = if_match.check(&project)?;
+
+    let mut tx = pool.begin().await?;
+    let snapshot = ProjectRevision::fetch_snapshot(&mut tx, project.id, revision)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    project.title = snapshot.title;
+    project.description = snapshot.description;
+    project.image = snapshot.image;
+    project.color = snapshot.color;
+    project.views = snapshot.views;
+    project.assets = snapshot.assets;
+    project.geometries = snapshot.geometries;
+    project.modified = Some(Utc::now());
+    project
+        .update(&mut tx, current_revision, &access.claims.email)
+        .await?;
+    tx.commit().await?;
+
+    Ok(([(ETAG, etag(project.revision))], Json(project)))
+}
@@ -0,0 +1,285 @@
+use anyhow::Context;
+use axum::http::header::HeaderName;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, Postgres, QueryBuilder, Row};
+use uuid::Uuid;
+
+use crate::projects::{Project, ProjectRole};
+use crate::{Error, Result};
+
+/// The response header containing the cursor of the next page of projects.
+pub const NEXT_CURSOR: HeaderName = HeaderName::from_static("x-next-cursor");
+
+/// The largest number of projects that can be requested at once.
+const MAX_LIMIT: i64 = 200;
+
+/// The filters, sort order and page of a project search.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectQuery {
+    /// Text that the title or description of projects has to contain.
+    pub q: Option<String>,
+
+    /// The role that the member needs to have within projects.
+    pub role: Option<ProjectRole>,
+
+    pub color: Option<String>,
+
+    /// Whether projects need to be templates or not.
+    pub template: Option<bool>,
+
+    /// The time since which projects need to have been modified.
+    pub modified_after: Option<DateTime<Utc>>,
+
+    /// The time before which projects need to have been modified.
+    pub modified_before: Option<DateTime<Utc>>,
+
+    /// An area in WGS84, as `minLon,minLat,maxLon,maxLat`,
+    /// that has to intersect the geometries of projects.
+    pub bbox: Option<String>,
+
+    #[serde(default)]
+    pub sort: ProjectSort,
+
+    /// Defaults to descending, except when sorting by title.
+    pub order: Option<SortOrder>,
+
+    /// The number of projects per page.
+    /// If not set, all matching projects are returned at once.
+    pub limit: Option<i64>,
+
+    /// The cursor of the page to return, as received from the previous page.
+    pub cursor: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectSort {
+    #[default]
+    Created,
+    Modified,
+    Title,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// A page of project ids.
+pub struct ProjectPage {
+    pub ids: Vec<Uuid>,
+
+    /// The cursor of the next page, if there is one.
+    pub next_cursor: Option<String>,
+}
+
+impl ProjectPage {
+    /// The response headers announcing the next page.
+    pub fn headers(&self) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        if let Some(cursor) = &self.next_cursor {
+            headers.insert(
+                NEXT_CURSOR,
+                HeaderValue::from_str(cursor).context("Invalid cursor")?,
+            );
+        }
+        Ok(headers)
+    }
+}
+
+/// The position after which the next page starts.
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    sort: ProjectSort,
+    key: String,
+    id: Uuid,
+}
+
+impl ProjectSort {
+    /// The expression by which projects are sorted, and the SQL type of its value.
+    fn key(&self) -> (&'static str, &'static str) {
+        match self {
+            ProjectSort::Created => ("p.created", "timestamptz"),
+            ProjectSort::Modified => ("COALESCE(p.modified, p.created)", "timestamptz"),
+            ProjectSort::Title => ("lower(p.title)", "text"),
+        }
+    }
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn decode(cursor: &str, sort: ProjectSort) -> Result<Self> {
+        let invalid = || Error::Api(StatusCode::BAD_REQUEST, "Invalid cursor.");
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+        let cursor: Cursor = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+        if cursor.sort != sort {
+            return Err(invalid());
+        }
+        Ok(cursor)
+    }
+}
+
+/// Parses a bounding box given as `minLon,minLat,maxLon,maxLat`.
+fn parse_bbox(bbox: &str) -> Result<[f64; 4]> {
+    let invalid = || {
+        Error::Api(
+            StatusCode::BAD_REQUEST,
+            "Invalid bbox, expected `minLon,minLat,maxLon,maxLat`.",
+        )
+    };
+    let values = bbox
+        .split(',')
+        .map(|it| it.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    match values[..] {
+        [min_lon, min_lat, max_lon, max_lat] if min_lon <= max_lon && min_lat <= max_lat => {
+            Ok([min_lon, min_lat, max_lon, max_lat])
+        }
+        _ => Err(invalid()),
+    }
+}
+
+impl Project {
+    /// Searches the projects of which the user with the given email is a member,
+    /// or all projects if no email is given.
+    pub async fn search(
+        conn: &mut PgConnection,
+        member: Option<&str>,
+        query: &ProjectQuery,
+    ) -> Result<ProjectPage> {
+        let (key, key_type) = query.sort.key();
+        let order = query.order.unwrap_or(match query.sort {
+            ProjectSort::Title => SortOrder::Asc,
+            _ => SortOrder::Desc,
+        });
+        let limit = query.limit.map(|it| it.clamp(1, MAX_LIMIT));
+
+        let mut sql = QueryBuilder::<Postgres>::new("SELECT p.id, ");
+        sql.push(key);
+        sql.push("::text AS key FROM projects p");
+        match member {
+            Some(email) => {
+                sql.push(" JOIN project_members m ON m.project_id = p.id");
+                sql.push(" WHERE p.deleted IS NULL AND m.email = ");
+                sql.push_bind(email.to_lowercase());
+                if let Some(role) = query.role {
+                    sql.push(" AND m.role = ").push_bind(role);
+                }
+            }
+            None if query.role.is_some() => {
+                return Err(Error::Api(
+                    StatusCode::BAD_REQUEST,
+                    "Filtering by role requires a member.",
+                ));
+            }
+            None => {
+                sql.push(" WHERE p.deleted IS NULL");
+            }
+        }
+        if let Some(text) = query
+            .q
+            .as_deref()
+            .map(str::trim)
+            .filter(|it| !it.is_empty())
+        {
+            let pattern = format!(
+                "%{}%",
+                text.replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_")
+            );
+            sql.push(" AND (to_tsvector('simple', p.title || ' ' || COALESCE(p.description, ''))");
+            sql.push(" @@ websearch_to_tsquery('simple', ");
+            sql.push_bind(text.to_owned());
+            sql.push(") OR p.title ILIKE ").push_bind(pattern.clone());
+            sql.push(" OR p.description ILIKE ").push_bind(pattern);
+            sql.push(")");
+        }
+        if let Some(template) = query.template {
+            sql.push(" AND p.template = ").push_bind(template);
+        }
+        if let Some(color) = &query.color {
+            sql.push(" AND lower(p.color) = lower(")
+                .push_bind(color.clone())
+                .push(")");
+        }
+        if let Some(after) = query.modified_after {
+            sql.push(" AND COALESCE(p.modified, p.created) >= ")
+                .push_bind(after);
+        }
+        if let Some(before) = query.modified_before {
+            sql.push(" AND COALESCE(p.modified, p.created) < ")
+                .push_bind(before);
+        }
+        if let Some(bbox) = &query.bbox {
+            let [min_lon, min_lat, max_lon, max_lat] = parse_bbox(bbox)?;
+            sql.push(" AND EXISTS (SELECT 1 FROM project_geometries g");
+            sql.push(" WHERE g.project_id = p.id AND g.extent && box(point(");
+            sql.push_bind(min_lon).push(", ").push_bind(min_lat);
+            sql.push("), point(");
+            sql.push_bind(max_lon).push(", ").push_bind(max_lat);
+            sql.push(")))");
+        }
+
+        let (comparison, direction) = match order {
+            SortOrder::Asc => (">", "ASC"),
+            SortOrder::Desc => ("<", "DESC"),
+        };
+        if let Some(cursor) = &query.cursor {
+            let cursor = Cursor::decode(cursor, query.sort)?;
+            sql.push(format_args!(" AND ({key}, p.id) {comparison} ("));
+            sql.push_bind(cursor.key);
+            sql.push(format_args!("::{key_type}, "));
+            sql.push_bind(cursor.id);
+            sql.push(")");
+        }
+        sql.push(format_args!(
+            " ORDER BY {key} {direction}, p.id {direction}"
+        ));
+        if let Some(limit) = limit {
+            // Fetch one more project to know whether there's a next page.
+            sql.push(" LIMIT ").push_bind(limit + 1);
+        }
+
+        let rows = sql.build().fetch_all(&mut *conn).await?;
+        let mut page = rows
+            .iter()
+            .map(|row| {
+                Ok((
+                    row.try_get::<Uuid, _>("id")?,
+                    row.try_get::<String, _>("key")?,
+                ))
+            })
+            .collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+        let mut next_cursor = None;
+        if let Some(limit) = limit
+            && page.len() as i64 > limit
+        {
+            page.truncate(limit as usize);
+            next_cursor = page.last().map(|(id, key)| {
+                Cursor {
+                    sort: query.sort,
+                    key: key.clone(),
+                    id: *id,
+                }
+                .encode()
+            });
+        }
+        Ok(ProjectPage {
+            ids: page.into_iter().map(|(id, _)| id).collect(),
+            next_cursor,
+        })
+    }
+}
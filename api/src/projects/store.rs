@@ -0,0 +1,456 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::Result;
+use crate::projects::assets::AssetFormat;
+use crate::projects::collaboration::notify_trashed;
+use crate::projects::{
+    Asset, Geometry, Member, Project, ProjectRevision, ProjectRole, View, conflict,
+};
+
+struct ProjectRow {
+    id: Uuid,
+    title: String,
+    description: Option<String>,
+    image: Option<String>,
+    color: String,
+    template: bool,
+    created: DateTime<Utc>,
+    modified: Option<DateTime<Utc>>,
+    revision: i32,
+}
+
+struct MemberRow {
+    project_id: Uuid,
+    email: String,
+    name: String,
+    surname: String,
+    role: ProjectRole,
+}
+
+struct ViewRow {
+    project_id: Uuid,
+    id: String,
+    title: String,
+    permalink: String,
+}
+
+struct AssetRow {
+    project_id: Uuid,
+    key: String,
+    name: String,
+    clamp_to_ground: Option<bool>,
+    original_name: Option<String>,
+    format: Option<AssetFormat>,
+}
+
+struct GeometryRow {
+    project_id: Uuid,
+    geometry: sqlx::types::Json<Geometry>,
+}
+
+impl Project {
+    /// Loads a project by its id.
+    /// Returns `None` if no such project exists.
+    pub async fn fetch(conn: &mut PgConnection, id: Uuid) -> Result<Option<Self>> {
+        Ok(Self::fetch_all(conn, &[id]).await?.pop())
+    }
+
+    /// Loads all projects with the given ids, in the order of `ids`.
+    /// Ids that don't match any project, or only one in the trash, are skipped.
+    pub async fn fetch_all(conn: &mut PgConnection, ids: &[Uuid]) -> Result<Vec<Self>> {
+        let rows = sqlx::query_as!(
+            ProjectRow,
+            r#"
+            SELECT id, title, description, image, color, template, created, modified, revision
+            FROM projects
+            WHERE id = ANY($1) AND deleted IS NULL
+            "#,
+            ids
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Self::load(conn, ids, rows).await
+    }
+
+    /// Loads all projects in the trash with the given ids, in the order of `ids`.
+    pub async fn fetch_all_trashed(conn: &mut PgConnection, ids: &[Uuid]) -> Result<Vec<Self>> {
+        let rows = sqlx::query_as!(
+            ProjectRow,
+            r#"
+            SELECT id, title, description, image, color, template, created, modified, revision
+            FROM projects
+            WHERE id = ANY($1) AND deleted IS NOT NULL
+            "#,
+            ids
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Self::load(conn, ids, rows).await
+    }
+
+    /// Loads the content of the given projects.
+    async fn load(
+        conn: &mut PgConnection,
+        ids: &[Uuid],
+        rows: Vec<ProjectRow>,
+    ) -> Result<Vec<Self>> {
+        let members = sqlx::query_as!(
+            MemberRow,
+            r#"
+            SELECT project_id, email, name, surname, role AS "role: ProjectRole"
+            FROM project_members
+            WHERE project_id = ANY($1)
+            ORDER BY email
+            "#,
+            ids
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let views = sqlx::query_as!(
+            ViewRow,
+            r#"
+            SELECT project_id, id, title, permalink
+            FROM project_views
+            WHERE project_id = ANY($1)
+            ORDER BY position
+            "#,
+            ids
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let assets = sqlx::query_as!(
+            AssetRow,
+            r#"
+            SELECT project_id, key, name, clamp_to_ground, original_name,
+                   format AS "format: AssetFormat"
+            FROM project_assets
+            WHERE project_id = ANY($1)
+            ORDER BY position
+            "#,
+            ids
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let geometries = sqlx::query_as!(
+            GeometryRow,
+            r#"
+            SELECT project_id, geometry AS "geometry: sqlx::types::Json<Geometry>"
+            FROM project_geometries
+            WHERE project_id = ANY($1)
+            ORDER BY position
+            "#,
+            ids
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut owners: HashMap<Uuid, Member> = HashMap::new();
+        let mut editors: HashMap<Uuid, Vec<Member>> = HashMap::new();
+        let mut viewers: HashMap<Uuid, Vec<Member>> = HashMap::new();
+        for row in members {
+            let member = Member {
+                email: row.email,
+                name: row.name,
+                surname: row.surname,
+            };
+            match row.role {
+                ProjectRole::Owner => {
+                    owners.insert(row.project_id, member);
+                }
+                ProjectRole::Editor => editors.entry(row.project_id).or_default().push(member),
+                ProjectRole::Viewer => viewers.entry(row.project_id).or_default().push(member),
+            }
+        }
+
+        let mut grouped_views: HashMap<Uuid, Vec<View>> = HashMap::new();
+        for row in views {
+            grouped_views.entry(row.project_id).or_default().push(View {
+                id: row.id,
+                title: row.title,
+                permalink: row.permalink,
+            });
+        }
+
+        let mut grouped_assets: HashMap<Uuid, Vec<Asset>> = HashMap::new();
+        for row in assets {
+            grouped_assets
+                .entry(row.project_id)
+                .or_default()
+                .push(Asset {
+                    name: row.name,
+                    key: row.key,
+                    clamp_to_ground: row.clamp_to_ground,
+                    original_name: row.original_name,
+                    format: row.format,
+                });
+        }
+
+        let mut grouped_geometries: HashMap<Uuid, Vec<Geometry>> = HashMap::new();
+        for row in geometries {
+            grouped_geometries
+                .entry(row.project_id)
+                .or_default()
+                .push(row.geometry.0);
+        }
+
+        let mut projects: HashMap<Uuid, Project> = HashMap::new();
+        for row in rows {
+            let owner = owners
+                .remove(&row.id)
+                .ok_or_else(|| anyhow!("Project {} has no owner", row.id))?;
+            projects.insert(
+                row.id,
+                Project {
+                    id: row.id,
+                    title: row.title,
+                    description: row.description,
+                    created: row.created,
+                    modified: row.modified,
+                    revision: row.revision,
+                    image: row.image,
+                    color: row.color,
+                    template: row.template,
+                    views: grouped_views.remove(&row.id).unwrap_or_default(),
+                    assets: grouped_assets.remove(&row.id).unwrap_or_default(),
+                    owner,
+                    viewers: viewers.remove(&row.id).unwrap_or_default(),
+                    editors: editors.remove(&row.id).unwrap_or_default(),
+                    geometries: grouped_geometries.remove(&row.id).unwrap_or_default(),
+                },
+            );
+        }
+        Ok(ids.iter().filter_map(|id| projects.remove(id)).collect())
+    }
+
+    /// Inserts this project as a new project.
+    /// The project's initial revision is recorded as authored by `author`.
+    pub async fn insert(&mut self, conn: &mut PgConnection, author: &str) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO projects
+                (id, title, description, image, color, template, created, modified, revision)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            self.id,
+            self.title,
+            self.description,
+            self.image,
+            self.color,
+            self.template,
+            self.created,
+            self.modified,
+            self.revision,
+        )
+        .execute(&mut *conn)
+        .await?;
+        self.write_content(conn).await?;
+        ProjectRevision::record(conn, self, author).await
+    }
+
+    /// Overwrites the stored version of this project,
+    /// given that it is still at `expected_revision`.
+    ///
+    /// On success, the project's revision is advanced to the newly stored one,
+    /// which is recorded as authored by `author`.
+    /// If the stored project has been modified in the meantime, `409 Conflict` is returned.
+    pub async fn update(
+        &mut self,
+        conn: &mut PgConnection,
+        expected_revision: i32,
+        author: &str,
+    ) -> Result<()> {
+        let revision = sqlx::query_scalar!(
+            r#"
+            UPDATE projects
+            SET title = $3, description = $4, image = $5, color = $6, template = $7,
+                modified = $8, revision = revision + 1
+            WHERE id = $1 AND revision = $2 AND deleted IS NULL
+            RETURNING revision
+            "#,
+            self.id,
+            expected_revision,
+            self.title,
+            self.description,
+            self.image,
+            self.color,
+            self.template,
+            self.modified,
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(conflict)?;
+        self.revision = revision;
+
+        sqlx::query!("DELETE FROM project_members WHERE project_id = $1", self.id)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query!("DELETE FROM project_views WHERE project_id = $1", self.id)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query!("DELETE FROM project_assets WHERE project_id = $1", self.id)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query!(
+            "DELETE FROM project_geometries WHERE project_id = $1",
+            self.id
+        )
+        .execute(&mut *conn)
+        .await?;
+        self.write_content(conn).await?;
+        ProjectRevision::record(conn, self, author).await
+    }
+
+    /// Moves the project with the given id to the trash.
+    pub async fn trash(conn: &mut PgConnection, id: Uuid) -> Result<()> {
+        sqlx::query!(
+            "UPDATE projects SET deleted = now() WHERE id = $1 AND deleted IS NULL",
+            id
+        )
+        .execute(&mut *conn)
+        .await?;
+        notify_trashed(conn, id).await
+    }
+
+    /// Takes the project with the given id out of the trash.
+    pub async fn untrash(conn: &mut PgConnection, id: Uuid) -> Result<()> {
+        sqlx::query!("UPDATE projects SET deleted = NULL WHERE id = $1", id)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes the project with the given id, including all of its content.
+    pub async fn delete(conn: &mut PgConnection, id: Uuid) -> Result<()> {
+        sqlx::query!("DELETE FROM projects WHERE id = $1", id)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Inserts the members, views, assets and geometries of this project.
+    /// Expects the project to not have any of them stored yet.
+    ///
+    /// The geometries are given ids where missing and measured anew before being stored.
+    async fn write_content(&mut self, conn: &mut PgConnection) -> Result<()> {
+        self.assign_geometry_ids();
+        self.measure_geometries();
+        let members: Vec<(&Member, ProjectRole)> =
+            std::iter::once((&self.owner, ProjectRole::Owner))
+                .chain(self.editors.iter().map(|it| (it, ProjectRole::Editor)))
+                .chain(self.viewers.iter().map(|it| (it, ProjectRole::Viewer)))
+                .collect();
+        let emails: Vec<String> = members
+            .iter()
+            .map(|(it, _)| it.email.to_lowercase())
+            .collect();
+        let names: Vec<String> = members.iter().map(|(it, _)| it.name.clone()).collect();
+        let surnames: Vec<String> = members.iter().map(|(it, _)| it.surname.clone()).collect();
+        let roles: Vec<ProjectRole> = members.iter().map(|(_, role)| *role).collect();
+
+        // Members appearing multiple times keep their highest role.
+        sqlx::query!(
+            r#"
+            INSERT INTO project_members (project_id, email, name, surname, role)
+            SELECT DISTINCT ON (email) $1, email, name, surname, role
+            FROM UNNEST($2::text[], $3::text[], $4::text[], $5::project_role[])
+                AS member(email, name, surname, role)
+            ORDER BY email, role DESC
+            "#,
+            self.id,
+            &emails,
+            &names,
+            &surnames,
+            &roles as &[ProjectRole],
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO project_views (project_id, position, id, title, permalink)
+            SELECT $1, position - 1, id, title, permalink
+            FROM UNNEST($2::text[], $3::text[], $4::text[])
+                WITH ORDINALITY AS view(id, title, permalink, position)
+            "#,
+            self.id,
+            &self
+                .views
+                .iter()
+                .map(|it| it.id.clone())
+                .collect::<Vec<_>>(),
+            &self
+                .views
+                .iter()
+                .map(|it| it.title.clone())
+                .collect::<Vec<_>>(),
+            &self
+                .views
+                .iter()
+                .map(|it| it.permalink.clone())
+                .collect::<Vec<_>>(),
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO project_assets
+                (project_id, position, key, name, clamp_to_ground, original_name, format)
+            SELECT $1, position - 1, key, name, clamp_to_ground, original_name, format
+            FROM UNNEST($2::text[], $3::text[], $4::boolean[], $5::text[], $6::asset_format[])
+                WITH ORDINALITY
+                AS asset(key, name, clamp_to_ground, original_name, format, position)
+            "#,
+            self.id,
+            &self
+                .assets
+                .iter()
+                .map(|it| it.key.clone())
+                .collect::<Vec<_>>(),
+            &self
+                .assets
+                .iter()
+                .map(|it| it.name.clone())
+                .collect::<Vec<_>>(),
+            &self
+                .assets
+                .iter()
+                .map(|it| it.clamp_to_ground)
+                .collect::<Vec<_>>() as &[Option<bool>],
+            &self
+                .assets
+                .iter()
+                .map(|it| it.original_name.clone())
+                .collect::<Vec<_>>() as &[Option<String>],
+            &self.assets.iter().map(|it| it.format).collect::<Vec<_>>() as &[Option<AssetFormat>],
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO project_geometries (project_id, position, id, geometry)
+            SELECT $1, position - 1, geometry->>'id', geometry
+            FROM UNNEST($2::jsonb[]) WITH ORDINALITY AS geometry(geometry, position)
+            "#,
+            self.id,
+            &self
+                .geometries
+                .iter()
+                .map(sqlx::types::Json)
+                .collect::<Vec<_>>() as &[sqlx::types::Json<&Geometry>],
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use aws_sdk_s3::Client;
+use axum::extract::{Extension, Json};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::Utc;
+use futures_util::{StreamExt, TryStreamExt, stream};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::audit::{AuditAction, AuditEntry, AuditTarget};
+use crate::projects::assets::copy_assets;
+use crate::projects::{Asset, Member, Project, ProjectAccess, Viewer};
+use crate::validation::{FieldError, ValidJson};
+use crate::{Error, Result};
+
+/// The largest number of projects that can be created from a template at once.
+const MAX_INSTANCES: usize = 100;
+
+/// The number of projects whose assets are copied concurrently.
+const ASSET_COPY_CONCURRENCY: usize = 8;
+
+/// A project to create from a template.
+#[derive(Deserialize, Debug)]
+pub struct TemplateInstance {
+    pub title: String,
+    /// Defaults to the template's description.
+    pub description: Option<String>,
+    #[serde(default)]
+    pub viewers: Vec<Member>,
+    #[serde(default)]
+    pub editors: Vec<Member>,
+}
+
+impl Project {
+    /// Creates a new project owned by `owner` from this template.
+    /// The views and geometries are copied, while the assets are expected to have been copied already.
+    fn instantiate(
+        &self,
+        instance: TemplateInstance,
+        owner: Member,
+        assets: Vec<Asset>,
+    ) -> Project {
+        Project {
+            id: Uuid::new_v4(),
+            title: instance.title,
+            description: instance.description.or_else(|| self.description.clone()),
+            created: Utc::now(),
+            modified: None,
+            revision: 1,
+            image: self.image.clone(),
+            color: self.color.clone(),
+            template: false,
+            views: self.views.clone(),
+            assets,
+            owner,
+            viewers: instance.viewers,
+            editors: instance.editors,
+            geometries: self.geometries.clone(),
+        }
+    }
+}
+
+/// Creates one project per entry from a template, and responds with their ids.
+///
+/// Each project is owned by the current user, and receives its own copies of the template's assets.
+/// The projects are either all created, or none of them are.
+#[axum_macros::debug_handler]
+pub async fn create_from_template(
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    access: ProjectAccess<Viewer>,
+    ValidJson(instances): ValidJson<Vec<TemplateInstance>>,
+) -> Result<impl IntoResponse> {
+    let template = access.project;
+    if !template.template {
+        return Err(Error::Api(
+            StatusCode::BAD_REQUEST,
+            "Project is not a template.",
+        ));
+    }
+    if instances.is_empty() || instances.len() > MAX_INSTANCES {
+        return Err(Error::Validation(vec![FieldError::new(
+            String::new(),
+            format!("Must contain between 1 and {MAX_INSTANCES} projects."),
+        )]));
+    }
+
+    let owner = Member {
+        email: access.claims.email.to_lowercase(),
+        name: access.claims.given_name.clone(),
+        surname: access.claims.family_name.clone(),
+    };
+    // The owner is added to each project anyway, so they can't be listed as another member.
+    let mut errors = Vec::new();
+    for (i, instance) in instances.iter().enumerate() {
+        for (name, members) in [
+            ("viewers", &instance.viewers),
+            ("editors", &instance.editors),
+        ] {
+            for (j, member) in members.iter().enumerate() {
+                if member.email.to_lowercase() == owner.email {
+                    errors.push(FieldError::new(
+                        format!("[{i}].{name}[{j}].email"),
+                        "Must not be the owner.",
+                    ));
+                }
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(Error::Validation(errors));
+    }
+
+    let copies: Vec<Vec<Asset>> = stream::iter(0..instances.len())
+        .map(|_| copy_assets(&client, &template.assets))
+        .buffered(ASSET_COPY_CONCURRENCY)
+        .try_collect()
+        .await?;
+    let mut projects: Vec<Project> = instances
+        .into_iter()
+        .zip(copies)
+        .map(|(instance, assets)| template.instantiate(instance, owner.clone(), assets))
+        .collect();
+
+    // Copied assets are left to the garbage collection if the projects can't be saved.
+    let mut tx = pool.begin().await?;
+    for project in &mut projects {
+        project.insert(&mut tx, &owner.email).await?;
+        let mut diff = project.audit_summary();
+        diff["template"] = json!(template.id);
+        AuditEntry::record(
+            &mut tx,
+            &owner.email,
+            AuditAction::ProjectCreated,
+            AuditTarget::Project(project.id),
+            diff,
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    let ids: Vec<Uuid> = projects.iter().map(|it| it.id).collect();
+    Ok((StatusCode::CREATED, Json(ids)))
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use aws_sdk_s3::Client;
+use axum::extract::{Extension, Json, Path};
+use axum::http::header::ETAG;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::audit::{AuditAction, AuditEntry, AuditTarget};
+use crate::auth::Claims;
+use crate::projects::assets::delete_assets;
+use crate::projects::{Project, ProjectRevision, ProjectRole, etag};
+use crate::{Error, Result};
+
+/// The actor recorded in the audit log for projects purged from the trash.
+const PURGE_ACTOR: &str = "system";
+
+/// Configuration of the trash to which deleted projects are moved.
+#[derive(clap::Parser, Debug, Clone)]
+pub struct ProjectTrash {
+    /// The time for which deleted projects are kept in the trash before being purged, in seconds.
+    #[clap(long, env, default_value = "2592000")]
+    pub project_trash_retention: u64,
+
+    /// The interval at which expired projects are purged from the trash, in seconds.
+    /// Zero disables the purge, keeping deleted projects in the trash indefinitely.
+    #[clap(long, env, default_value = "3600")]
+    pub project_trash_purge_interval: u64,
+}
+
+/// A project in the trash.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashedProject {
+    #[serde(flatten)]
+    pub project: Project,
+
+    /// The time at which the project was deleted.
+    pub deleted: DateTime<Utc>,
+
+    /// The time after which the project is purged from the trash.
+    pub purge_after: DateTime<Utc>,
+}
+
+impl ProjectTrash {
+    fn retention(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.project_trash_retention as i64)
+    }
+
+    /// Permanently deletes the projects that have been in the trash for longer than the retention,
+    /// along with their assets. Returns the ids of the purged projects.
+    ///
+    /// The revisions of purged projects are kept, so that administrators can still restore them.
+    pub async fn purge(&self, pool: &PgPool, client: &Client) -> Result<Vec<Uuid>> {
+        let cutoff = Utc::now() - self.retention();
+        let ids = sqlx::query_scalar!("SELECT id FROM projects WHERE deleted < $1", cutoff)
+            .fetch_all(pool)
+            .await?;
+
+        for id in &ids {
+            purge_project(pool, client, *id).await?;
+        }
+        Ok(ids)
+    }
+
+    /// Purges expired projects at the configured interval, unless it is zero.
+    pub fn spawn(self, pool: PgPool, client: Client) {
+        if self.project_trash_purge_interval == 0 {
+            tracing::info!("Purging of the project trash is disabled.");
+            return;
+        }
+        tokio::spawn(async move {
+            let period = Duration::from_secs(self.project_trash_purge_interval);
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                match self.purge(&pool, &client).await {
+                    Ok(ids) if ids.is_empty() => {}
+                    Ok(ids) => tracing::info!("Purged {} projects from the trash.", ids.len()),
+                    Err(err) => tracing::error!("Failed to purge the project trash: {err:?}"),
+                }
+            }
+        });
+    }
+}
+
+/// Deletes a project and its assets, including the ones only referenced by past revisions.
+///
+/// The assets are deleted last, so that the project is kept in the trash if that fails.
+async fn purge_project(pool: &PgPool, client: &Client, id: Uuid) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    let Some(project) = fetch_trashed_for_update(&mut tx, id).await? else {
+        return Ok(());
+    };
+    let mut assets = project.assets.clone();
+    assets.extend(ProjectRevision::fetch_assets(&mut tx, id).await?);
+    assets.sort_by(|a, b| a.key.cmp(&b.key));
+    assets.dedup_by(|a, b| a.key == b.key);
+
+    Project::delete(&mut tx, id).await?;
+    AuditEntry::record(
+        &mut tx,
+        PURGE_ACTOR,
+        AuditAction::ProjectPurged,
+        AuditTarget::Project(id),
+        project.audit_summary(),
+    )
+    .await?;
+    if !assets.is_empty() {
+        let keys: Vec<&str> = assets.iter().map(|it| it.key.as_str()).collect();
+        AuditEntry::record(
+            &mut tx,
+            PURGE_ACTOR,
+            AuditAction::AssetsDeleted,
+            AuditTarget::Project(id),
+            json!({ "keys": keys }),
+        )
+        .await?;
+        delete_assets(client, &assets).await?;
+    }
+    tx.commit().await?;
+    tracing::debug!("Purged project {id} and {} assets.", assets.len());
+    Ok(())
+}
+
+/// Lists the projects in the trash that are owned by the current user, most recently deleted first.
+#[axum_macros::debug_handler]
+pub async fn list_trash(
+    Extension(pool): Extension<PgPool>,
+    Extension(trash): Extension<Arc<ProjectTrash>>,
+    claims: Claims,
+) -> Result<Json<Vec<TrashedProject>>> {
+    let mut conn = pool.acquire().await?;
+    let rows = sqlx::query!(
+        r#"
+        SELECT p.id, p.deleted AS "deleted!"
+        FROM projects p
+        JOIN project_members m ON m.project_id = p.id
+        WHERE m.email = $1 AND m.role = 'owner' AND p.deleted IS NOT NULL
+        ORDER BY p.deleted DESC, p.id
+        "#,
+        claims.email.to_lowercase()
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let ids: Vec<Uuid> = rows.iter().map(|it| it.id).collect();
+    let mut deleted: HashMap<Uuid, DateTime<Utc>> =
+        rows.into_iter().map(|it| (it.id, it.deleted)).collect();
+    let projects = Project::fetch_all_trashed(&mut conn, &ids)
+        .await?
+        .into_iter()
+        .filter_map(|project| {
+            let deleted = deleted.remove(&project.id)?;
+            Some(TrashedProject {
+                project,
+                deleted,
+                purge_after: deleted + trash.retention(),
+            })
+        })
+        .collect();
+    Ok(Json(projects))
+}
+
+/// Takes a project owned by the current user out of the trash.
+#[axum_macros::debug_handler]
+pub async fn restore_project(
+    Extension(pool): Extension<PgPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    let mut tx = pool.begin().await?;
+    let project = fetch_trashed_for_update(&mut tx, id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    match project.role_of(&claims.email) {
+        None => return Err(Error::NotFound),
+        Some(role) if role < ProjectRole::Owner => return Err(Error::Forbidden),
+        Some(_) => {}
+    }
+    restore(&mut tx, &project, &claims.email).await?;
+    tx.commit().await?;
+
+    Ok(([(ETAG, etag(project.revision))], Json(project)))
+}
+
+/// Loads and locks a project in the trash.
+pub(super) async fn fetch_trashed_for_update(
+    conn: &mut PgConnection,
+    id: Uuid,
+) -> Result<Option<Project>> {
+    let locked = sqlx::query!(
+        "SELECT id FROM projects WHERE id = $1 AND deleted IS NOT NULL FOR UPDATE",
+        id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    if locked.is_none() {
+        return Ok(None);
+    }
+    Ok(Project::fetch_all_trashed(conn, &[id]).await?.pop())
+}
+
+/// Takes a project out of the trash, and records it in the audit log.
+pub(super) async fn restore(conn: &mut PgConnection, project: &Project, actor: &str) -> Result<()> {
+    Project::untrash(conn, project.id).await?;
+    AuditEntry::record(
+        conn,
+        actor,
+        AuditAction::ProjectRestored,
+        AuditTarget::Project(project.id),
+        project.audit_summary(),
+    )
+    .await
+}
@@ -0,0 +1,184 @@
+use url::Url;
+
+use crate::projects::templates::TemplateInstance;
+use crate::projects::{CesiumColor, CreateProject, Geometry, GeometryType, Member, Project};
+use crate::validation::{FieldError, Validate, field};
+
+/// The largest number of positions that a single geometry may have.
+const MAX_POSITIONS: usize = 10_000;
+
+/// The range of heights above the WGS84 ellipsoid, in meters, within which positions are accepted.
+/// Generously covers everything from deep boreholes up to the stratosphere.
+const HEIGHT_RANGE: std::ops::RangeInclusive<f64> = -100_000.0..=100_000.0;
+
+impl GeometryType {
+    /// The number of positions that a geometry of this type needs at least.
+    fn min_positions(&self) -> usize {
+        match self {
+            GeometryType::Point => 1,
+            GeometryType::Line => 2,
+            GeometryType::Polygon | GeometryType::Rectangle => 3,
+            GeometryType::Unknown(_) => 0,
+        }
+    }
+}
+
+impl Validate for Geometry {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if let GeometryType::Unknown(_) = self.typ {
+            errors.push(FieldError::new(
+                field(path, "type"),
+                "Must be one of point, line, polygon or rectangle.",
+            ));
+        }
+        let positions = field(path, "positions");
+        let min_positions = self.typ.min_positions();
+        if self.positions.len() < min_positions {
+            errors.push(FieldError::new(
+                positions.clone(),
+                format!("Must contain at least {min_positions} positions."),
+            ));
+        }
+        if self.positions.len() > MAX_POSITIONS {
+            errors.push(FieldError::new(
+                positions.clone(),
+                format!("Must contain at most {MAX_POSITIONS} positions."),
+            ));
+        }
+        for (i, position) in self.positions.iter().enumerate() {
+            if !HEIGHT_RANGE.contains(&position.to_wgs84().height) {
+                errors.push(FieldError::new(
+                    format!("{positions}[{i}]"),
+                    "Must be near the surface of the earth.",
+                ));
+            }
+        }
+
+        if let Some(color) = &self.color {
+            color.validate(&field(path, "color"), errors);
+        }
+        validate_url(&self.website, &field(path, "website"), errors);
+        validate_url(&self.image, &field(path, "image"), errors);
+        validate_non_negative(self.depth, &field(path, "depth"), errors);
+        validate_non_negative(self.diameter, &field(path, "diameter"), errors);
+        if let Some(limits) = &self.volume_height_limits {
+            validate_non_negative(
+                limits.height.as_f64(),
+                &field(&field(path, "volumeHeightLimits"), "height"),
+                errors,
+            );
+        }
+    }
+}
+
+impl Validate for CesiumColor {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        let channels = [
+            ("red", &self.red),
+            ("green", &self.green),
+            ("blue", &self.blue),
+            ("alpha", &self.alpha),
+        ];
+        for (name, value) in channels {
+            if !(0.0..=1.0).contains(&value.as_f64().unwrap_or(f64::NAN)) {
+                errors.push(FieldError::new(
+                    field(path, name),
+                    "Must be between 0 and 1.",
+                ));
+            }
+        }
+    }
+}
+
+impl Validate for Project {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        self.geometries.validate(&field(path, "geometries"), errors);
+    }
+}
+
+impl Validate for CreateProject {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        self.geometries.validate(&field(path, "geometries"), errors);
+    }
+}
+
+impl Validate for TemplateInstance {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if self.title.trim().is_empty() {
+            errors.push(FieldError::new(field(path, "title"), "Must not be empty."));
+        }
+        let mut emails = Vec::new();
+        for (name, members) in [("viewers", &self.viewers), ("editors", &self.editors)] {
+            for (i, member) in members.iter().enumerate() {
+                let email = member.email.to_lowercase();
+                validate_member(member, &format!("{}[{i}]", field(path, name)), errors);
+                if emails.contains(&email) {
+                    errors.push(FieldError::new(
+                        format!("{}[{i}].email", field(path, name)),
+                        "Must only be listed once.",
+                    ));
+                }
+                emails.push(email);
+            }
+        }
+    }
+}
+
+fn validate_member(member: &Member, path: &str, errors: &mut Vec<FieldError>) {
+    let is_email = member
+        .email
+        .split_once('@')
+        .is_some_and(|(name, domain)| !name.is_empty() && !domain.is_empty());
+    if !is_email {
+        errors.push(FieldError::new(
+            field(path, "email"),
+            "Must be an email address.",
+        ));
+    }
+}
+
+/// Ensures that a link is an absolute HTTP(S) URL.
+/// Empty links are treated as absent.
+fn validate_url(value: &Option<String>, path: &str, errors: &mut Vec<FieldError>) {
+    let Some(value) = value.as_deref().filter(|it| !it.is_empty()) else {
+        return;
+    };
+    match Url::parse(value) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+        _ => errors.push(FieldError::new(
+            path.to_owned(),
+            "Must be an http or https URL.",
+        )),
+    }
+}
+
+fn validate_non_negative(value: Option<f64>, path: &str, errors: &mut Vec<FieldError>) {
+    if value.is_some_and(|it| it < 0.0) {
+        errors.push(FieldError::new(path.to_owned(), "Must not be negative."));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn unknown_geometry_types_are_read_but_not_written() {
+        let geometry: Geometry =
+            serde_json::from_value(json!({ "type": "circle", "positions": [] })).unwrap();
+        assert_eq!(geometry.typ, GeometryType::Unknown("circle".to_owned()));
+        assert_eq!(serde_json::to_value(&geometry).unwrap()["type"], "circle");
+
+        let mut errors = Vec::new();
+        geometry.validate("geometries[0]", &mut errors);
+        assert_eq!(
+            errors,
+            vec![FieldError::new(
+                "geometries[0].type".to_owned(),
+                "Must be one of point, line, polygon or rectangle.",
+            )]
+        );
+    }
+}
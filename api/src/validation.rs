@@ -0,0 +1,89 @@
+use axum::async_trait;
+use axum::body::Bytes;
+use axum::extract::{FromRequest, Request};
+use axum::http::StatusCode;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::{Error, Result};
+
+/// An invalid field of a request body.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct FieldError {
+    /// The path to the field, e.g. `geometries[0].positions`.
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: String, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+/// A value whose fields can be checked beyond what its type enforces.
+pub trait Validate {
+    /// Adds an error for each invalid field to `errors`.
+    /// The paths of the fields are relative to `path`.
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>);
+}
+
+impl<T: Validate> Validate for Vec<T> {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        for (i, item) in self.iter().enumerate() {
+            item.validate(&format!("{path}[{i}]"), errors);
+        }
+    }
+}
+
+/// Validates a value, failing with all of its invalid fields.
+pub fn validate(value: &impl Validate, path: &str) -> Result<()> {
+    let mut errors = Vec::new();
+    value.validate(path, &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Validation(errors))
+    }
+}
+
+/// Appends the name of a field to a path.
+pub fn field(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{path}.{name}")
+    }
+}
+
+/// A JSON request body that is validated after being deserialized.
+///
+/// Unlike [axum::Json], failures are returned as `422 Unprocessable Entity`
+/// listing the invalid fields, see [Error::Validation].
+pub struct ValidJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(|_| Error::Api(StatusCode::BAD_REQUEST, "Failed to read request body."))?;
+        let deserializer = &mut serde_json::Deserializer::from_slice(&body);
+        let value: T = serde_path_to_error::deserialize(deserializer).map_err(|e| {
+            let path = e.path().to_string();
+            let field = if path == "." { String::new() } else { path };
+            Error::Validation(vec![FieldError::new(field, e.into_inner().to_string())])
+        })?;
+        validate(&value, "")?;
+        Ok(Self(value))
+    }
+}
@@ -16,7 +16,7 @@ async fn spawn_app() -> Router {
         .setup_with(&Uuid::new_v4().to_string(), true)
         .await;
 
-    api::app(pool).await
+    api::app(pool, api::create_s3_client().await).await
 }
 
 #[tokio::test]
@@ -40,3 +40,23 @@ async fn health_check_works() {
     // Assert
     assert_eq!(response.status(), StatusCode::OK);
 }
+
+#[tokio::test]
+async fn projects_require_authentication() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/projects/{}", Uuid::new_v4()))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
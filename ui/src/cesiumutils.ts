@@ -109,16 +109,23 @@ export type Measurements = {
   area?: number;
 };
 /**
- * Returns measurements for geometry
+ * Returns measurements for geometry.
+ * The rings of polygons and rectangles are closed, the same way as the API measures them.
  */
 export function getMeasurements(
   positions: Cartesian3[],
   type: GeometryTypes,
 ): Measurements {
+  const isArea =
+    (type === 'rectangle' || type === 'polygon') && positions.length > 2;
+  const ring =
+    isArea && !positions[0].equals(positions[positions.length - 1])
+      ? [...positions, positions[0]]
+      : positions;
   const segmentsLength: number[] = [];
-  positions.forEach((p, key) => {
+  ring.forEach((p, key) => {
     if (key > 0) {
-      segmentsLength.push(Cartesian3.distance(positions[key - 1], p) / 1000);
+      segmentsLength.push(Cartesian3.distance(ring[key - 1], p) / 1000);
     }
   });
   const result: Measurements = {
@@ -127,12 +134,8 @@ export function getMeasurements(
     positions,
     type,
   };
-  let perimeter = segmentsLength.reduce((a, b) => a + b, 0);
-  if (type === 'rectangle') {
-    perimeter *= 2;
-  }
-  result.perimeter = perimeter;
-  if (type === 'rectangle' || (type === 'polygon' && positions.length > 2)) {
+  result.perimeter = segmentsLength.reduce((a, b) => a + b, 0);
+  if (isArea) {
     result.area = getPolygonArea(positions);
   }
   return result;
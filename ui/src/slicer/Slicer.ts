@@ -281,13 +281,12 @@ export default class Slicer {
       };
     }
     const measurements = getMeasurements(positions, type);
-    const segmentsLength = measurements.segmentsLength;
     geomToCreate = {
       ...geomToCreate,
       ...measurements,
       area: measurements.area?.toFixed(3),
       perimeter: measurements.perimeter?.toFixed(3),
-      sidesLength: [segmentsLength[0], segmentsLength[1]],
+      sidesLength: measurements.segmentsLength,
       show: false,
     };
     ToolboxStore.setGeometryToCreate({ geometry: geomToCreate, slice: true });
@@ -165,8 +165,8 @@ export class GeometryController {
       area: measurements.area?.toFixed(3),
       perimeter: measurements.perimeter?.toFixed(3),
       sidesLength:
-        measurements.segmentsLength?.length > 1
-          ? [measurements.segmentsLength[0], measurements.segmentsLength[1]]
+        measurements.segmentsLength?.length > 0
+          ? measurements.segmentsLength
           : undefined,
       numberOfSegments: measurements.numberOfSegments,
       type: type,
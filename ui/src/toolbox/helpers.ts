@@ -191,14 +191,13 @@ export function getAreaProperties(entity: Entity, type: GeometryTypes) {
       ? entity.polyline!.positions!.getValue(julianDate)
       : entity.polygon!.hierarchy!.getValue(julianDate).positions;
   const measurements = getMeasurements(positions, type);
-  const segmentsLength = measurements.segmentsLength;
   return {
     ...props,
     type: type,
     area: measurements.area?.toFixed(3),
     perimeter: measurements.perimeter?.toFixed(3),
     numberOfSegments: measurements.numberOfSegments,
-    sidesLength: [segmentsLength[0], segmentsLength[1]],
+    sidesLength: measurements.segmentsLength,
   };
 }
 